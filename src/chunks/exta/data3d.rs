@@ -0,0 +1,90 @@
+use nom::bytes::complete::take;
+use nom::combinator::verify;
+use nom::IResult;
+use nom::number::complete::le_u32;
+
+// the 3d externals (SceneData, ...) are little endian unlike everything else
+// the payload is high entropy and padded to 16 bytes (1425 -> 1440 in the sample), probably encrypted
+
+/// the header of a 3d external, `data` is the undecoded payload
+pub struct Exta3DData<'a> {
+    // name_len: u32 LE = 20
+    // "CLIP_STUDIO_3D_DATA2"
+    // kind_len: u32 LE
+    pub kind: String, // "scene"
+    pub uuid: [u8; 16],
+    pub version: u32, // ?: u32 = 2
+    pub size: u32, // size of the decoded payload?
+    // data_size: u32
+    pub data: &'a [u8],
+}
+
+impl<'a> Exta3DData<'a> {
+    fn parse_string(inp: &[u8]) -> IResult<&[u8], &[u8]> {
+        let (i, len) = verify(le_u32, |x| { *x < 256 })(inp)?;
+        take(len)(i)
+    }
+
+    pub fn parse(inp: &'a [u8]) -> IResult<&'a [u8], Self> {
+        let (i, _) = verify(Self::parse_string, |s: &[u8]| { s.starts_with(b"CLIP_STUDIO_3D_DATA") })(inp)?;
+        let (i, kind) = Self::parse_string(i)?;
+        let (i, uuid_slice) = take(16u32)(i)?;
+
+        let (i, version) = le_u32(i)?;
+        let (i, size) = le_u32(i)?;
+        let (i, data_size) = le_u32(i)?;
+        let (i, data) = take(data_size)(i)?;
+
+        //---
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(uuid_slice);
+
+        Ok((i, Exta3DData { kind: String::from_utf8_lossy(kind).to_string(), uuid, version, size, data }))
+    }
+
+    /// the uuid in the usual hyphenated form
+    pub fn uuid_string(&self) -> String {
+        let h: String = self.uuid.iter().map(|b| { format!("{b:02x}") }).collect();
+        format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data3d(magic: &[u8], payload: &[u8]) -> Vec<u8> {
+        let mut b = vec![];
+        b.extend_from_slice(&(magic.len() as u32).to_le_bytes());
+        b.extend_from_slice(magic);
+        b.extend_from_slice(&5u32.to_le_bytes());
+        b.extend_from_slice(b"scene");
+        b.extend((0u8..16).collect::<Vec<_>>());
+        for v in [2u32, 3, payload.len() as u32] {
+            b.extend_from_slice(&v.to_le_bytes());
+        }
+        b.extend_from_slice(payload);
+        b
+    }
+
+    #[test]
+    fn parse_header() {
+        let data = data3d(b"CLIP_STUDIO_3D_DATA2", &[0xaa; 16]);
+        let (rem, d) = Exta3DData::parse(&data).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(d.kind, "scene");
+        assert_eq!((d.version, d.size), (2, 3));
+        assert_eq!(d.data, &[0xaa; 16]);
+        assert_eq!(d.uuid_string(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+    }
+
+    #[test]
+    fn reject_bad_data() {
+        assert!(Exta3DData::parse(&data3d(b"SOMETHING_ELSE", &[])).is_err());
+
+        let mut data = data3d(b"CLIP_STUDIO_3D_DATA2", &[0; 16]);
+        data.truncate(data.len() - 1);
+        assert!(Exta3DData::parse(&data).is_err());
+    }
+}
@@ -1,24 +1,23 @@
-use std::io::Read;
-
 use nom::bytes::complete::{tag, take};
 use nom::combinator::verify;
 use nom::IResult;
 use nom::number::complete::be_u64;
-use crate::ExtaOffscreen;
+use crate::{Exta3DData, ExtaOffscreen, ExtaVector};
 
+pub mod data3d;
 pub mod offscreen;
-mod vector;
+pub mod vector;
 
 pub enum ClipExtaBody<'a> {
     Offscreen(ExtaOffscreen<'a>), // block data
-    // VectorObjects,
+    VectorObjects(ExtaVector), // vector strokes
     // ModelBank3D,
     // ModelLoader3D,
-    // Track, // is there 2 types of this? (or is it just 2 of the same type)
+    Track(&'a [u8]), // TrackActionMixer(2), not decoded yet (is there 2 types of this? or is it just 2 of the same type)
     // ItemBinary,
-    // SceneData3D,
+    SceneData3D(Exta3DData<'a>), // Manager3DOd.SceneData, payload not decoded
     // ModelData3D,
-    // TimeLapse
+    // TimeLapse, // TimeLapseBlob.BlobData, format unknown
     Unknown,
 }
 
@@ -7,10 +7,10 @@ use nom::IResult;
 use nom::multi::many1;
 use nom::number::complete::be_u32;
 
-mod tags {
+pub(crate) mod tags {
     use std::sync::LazyLock;
 
-    fn blockdata_tag(s: &'static str) -> Vec<u8> {
+    pub(crate) fn blockdata_tag(s: &'static str) -> Vec<u8> {
         let mut v: Vec<u8> = s.encode_utf16()
             .flat_map(|x| { x.to_be_bytes() })
             .collect();
@@ -21,11 +21,11 @@ mod tags {
         sz
     }
 
-    pub(super) const BEGIN_CHUNK: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockDataBeginChunk") });
-    pub(super) const END_CHUNK: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockDataEndChunk") });
+    pub(super) static BEGIN_CHUNK: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockDataBeginChunk") });
+    pub(super) static END_CHUNK: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockDataEndChunk") });
 
-    pub(crate) const STATUS: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockStatus") });
-    pub(crate) const CHECKSUM: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockCheckSum") });
+    pub(crate) static STATUS: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockStatus") });
+    pub(crate) static CHECKSUM: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("BlockCheckSum") });
 }
 
 pub struct BlockData<'a> {
@@ -35,7 +35,7 @@ pub struct BlockData<'a> {
 }
 
 impl<'a> BlockData<'a> {
-    pub fn parse(inp: &'a [u8]) -> IResult<&[u8], Self> {
+    pub fn parse(inp: &'a [u8]) -> IResult<&'a [u8], Self> {
         let (i, size) = be_u32(inp)?;
         let (remaining, data) = take(size)(i)?;
 
@@ -56,27 +56,33 @@ pub struct BlockDataChunk<'a> {
     // size mark: u32 >= 104
     //----
     // BlockDataBeginChunk : block tag
-    // ???: [16 bytes]
+    pub index: u32, // position in the offscreen's block grid (row major)
+    pub decompressed_size: u32, // 327680 for colour, 65536 for masks
+    pub width: u32, // always 256 so far
+    pub height: u32,
     // data_flag : u32 == 0 || 1, whether the data section exists
     pub data: Option<BlockData<'a>>, //< treat as all 0s in decompression if None
     // BlockDataEndChunk : block tag
 }
 
 impl<'a> BlockDataChunk<'a> {
-    fn parse_inner(inp: &'a [u8]) -> IResult<&[u8], Self> {
+    fn parse_inner(inp: &'a [u8]) -> IResult<&'a [u8], Self> {
         let (i, _) = tag(tags::BEGIN_CHUNK.as_slice())(inp)?;
 
-        let (i, _) = take(16u32)(i)?;
+        let (i, index) = be_u32(i)?;
+        let (i, decompressed_size) = be_u32(i)?;
+        let (i, width) = be_u32(i)?;
+        let (i, height) = be_u32(i)?;
 
         let (i, data_flag) = verify(be_u32, |x| { *x == 0 || *x == 1 })(i)?;
         let (i, data) = cond(data_flag == 1, BlockData::parse)(i)?;
 
         let (i, _) = tag(tags::END_CHUNK.as_slice())(i)?;
 
-        Ok((i, BlockDataChunk { data }))
+        Ok((i, BlockDataChunk { index, decompressed_size, width, height, data }))
     }
 
-    pub fn parse(inp: &'a [u8]) -> IResult<&[u8], Self> {
+    pub fn parse(inp: &'a [u8]) -> IResult<&'a [u8], Self> {
         let (_, size) = verify(be_u32, |x| { *x >= 104 })(inp)?;
 
         let (remaining, inner) = take(size)(inp)?;
@@ -87,12 +93,19 @@ impl<'a> BlockDataChunk<'a> {
         Ok((remaining, dc))
     }
 
-    pub fn decompress(&self) -> Vec<u8> {
-        self.data.as_ref().map_or(vec![0u8; 327680], |d| {
-            let mut buf = Vec::with_capacity(327680);
-            ZlibDecoder::new(d.zlib_data).read_to_end(&mut buf).unwrap();
-            buf
-        })
+    /// the inflated block, or None when the zlib stream is corrupt
+    pub fn decompress(&self) -> Option<Vec<u8>> {
+        let size = self.decompressed_size as usize;
+
+        match &self.data {
+            Some(d) => {
+                // deflate can't expand by more than ~1032:1 so don't trust a bigger stated size
+                let mut buf = Vec::with_capacity(size.min(d.zlib_data.len().saturating_mul(1032)));
+                ZlibDecoder::new(d.zlib_data).read_to_end(&mut buf).ok()?;
+                Some(buf)
+            }
+            None => Some(vec![0u8; size]),
+        }
     }
 }
 
@@ -121,7 +134,7 @@ impl<'a> ExtaOffscreen<'a> {
         take(s1 * s2)(i)
     }
 
-    pub fn parse(inp: &'a [u8]) -> IResult<&[u8], Self> {
+    pub fn parse(inp: &'a [u8]) -> IResult<&'a [u8], Self> {
         let (i, chunks) = many1(BlockDataChunk::parse)(inp)?;
 
         // todo: actually use the checksums
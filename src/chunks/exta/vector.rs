@@ -0,0 +1,198 @@
+use nom::bytes::complete::take;
+use nom::combinator::verify;
+use nom::IResult;
+use nom::multi::{count, many0};
+use nom::number::complete::{be_f32, be_f64, be_i32, be_u32};
+
+use crate::chunks::parse_rgb;
+
+// most of the per point values are 1.0 in every file so far
+// so which of them is pressure/width/opacity is a guess
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct VectorPoint {
+    pub x: f64,
+    pub y: f64,
+    pub bounds: [i32; 4], // left, top, right, bottom of the segment
+    // ?: u32 (3 for frame borders, 0 otherwise)
+    pub pressure: f32,
+    pub width: f32,
+    // ?: [u32; 2]
+    pub opacity: f32,
+    // ?: [f32; 2] = 1.0
+    // ?: [f32; 3] (frame border corners have a scaled direction vector here)
+    // ?: f32
+    // stroke id?: u32
+    // ?: u32
+}
+
+impl VectorPoint {
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (i, x) = be_f64(inp)?;
+        let (i, y) = be_f64(i)?;
+
+        let (i, b) = count(be_i32, 4)(i)?;
+        let (i, _) = be_u32(i)?;
+
+        let (i, pressure) = be_f32(i)?;
+        let (i, width) = be_f32(i)?;
+        let (i, _) = take(8u32)(i)?;
+        let (i, opacity) = be_f32(i)?;
+
+        Ok((i, VectorPoint { x, y, bounds: [b[0], b[1], b[2], b[3]], pressure, width, opacity }))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VectorStroke {
+    // header_size: u32 (88, 96 for frame borders)
+    // ?: u32 = 72
+    // point_size: u32 = 88
+    // ?: u32 = 88
+    // point_count: u32
+    pub flags: u32,
+    pub bounds: [i32; 4], // left, top, right, bottom
+    pub main_color: [u8; 3], // stroke colour
+    pub sub_color: [u8; 3],
+    pub opacity: f64,
+    pub antialias: u32, // ?: u32 (3, 0x410 for frame borders) low bits guessed to be the 0-3 tool setting
+    // ?: [u8; header_size - 88]
+    pub brush_size: f64,
+    // stroke id?: u32
+    pub points: Vec<VectorPoint>,
+}
+
+impl VectorStroke {
+    fn parse_header(inp: &[u8]) -> IResult<&[u8], (u32, u32, Self)> {
+        let (i, header_size) = verify(be_u32, |x| { *x >= 88 })(inp)?;
+        let (i, _) = be_u32(i)?;
+        let (i, point_size) = verify(be_u32, |x| { *x >= 56 })(i)?;
+        let (i, _) = be_u32(i)?;
+        let (i, point_count) = be_u32(i)?;
+
+        let (i, flags) = be_u32(i)?;
+        let (i, b) = count(be_i32, 4)(i)?;
+
+        let (i, main_color) = parse_rgb(i)?;
+        let (i, sub_color) = parse_rgb(i)?;
+
+        let (i, opacity) = be_f64(i)?;
+        let (i, antialias) = be_u32(i)?;
+        let (i, _) = take(header_size - 88)(i)?;
+        let (i, brush_size) = be_f64(i)?;
+        let (i, _) = be_u32(i)?;
+
+        let stroke = VectorStroke {
+            flags,
+            bounds: [b[0], b[1], b[2], b[3]],
+            main_color,
+            sub_color,
+            opacity,
+            antialias: antialias & 0xf,
+            brush_size,
+            points: vec![],
+        };
+
+        Ok((i, (point_size, point_count, stroke)))
+    }
+
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (mut i, (point_size, point_count, mut stroke)) = Self::parse_header(inp)?;
+
+        for _ in 0..point_count {
+            let (rem, point) = take(point_size)(i)?;
+            stroke.points.push(VectorPoint::parse(point)?.1);
+            i = rem;
+        }
+
+        Ok((i, stroke))
+    }
+
+    /// guessed, only seen on (closed) frame borders
+    pub fn is_closed(&self) -> bool {
+        self.flags & 0x10 != 0
+    }
+
+    pub fn is_antialiased(&self) -> bool {
+        self.antialias != 0
+    }
+}
+
+pub struct ExtaVector {
+    pub strokes: Vec<VectorStroke>,
+}
+
+impl ExtaVector {
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (i, strokes) = many0(VectorStroke::parse)(inp)?;
+        Ok((i, ExtaVector { strokes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stroke(points: &[(f64, f64)]) -> Vec<u8> {
+        let mut b = vec![];
+        for v in [88u32, 72, 88, 88, points.len() as u32, 0x10] {
+            b.extend_from_slice(&v.to_be_bytes());
+        }
+        for v in [1i32, 2, 30, 40] {
+            b.extend_from_slice(&v.to_be_bytes());
+        }
+        for c in [0xffffffffu32, 0x80808080, 0, 0, 0, 0] {
+            b.extend_from_slice(&c.to_be_bytes());
+        }
+        b.extend_from_slice(&0.5f64.to_be_bytes());
+        b.extend_from_slice(&0x413u32.to_be_bytes());
+        b.extend_from_slice(&3.0f64.to_be_bytes());
+        b.extend_from_slice(&0u32.to_be_bytes());
+        assert_eq!(b.len(), 88);
+
+        for (x, y) in points {
+            let start = b.len();
+            b.extend_from_slice(&x.to_be_bytes());
+            b.extend_from_slice(&y.to_be_bytes());
+            b.extend_from_slice(&[0; 20]);
+            b.extend_from_slice(&0.75f32.to_be_bytes());
+            b.extend_from_slice(&1.0f32.to_be_bytes());
+            b.extend_from_slice(&[0; 8]);
+            b.extend_from_slice(&0.5f32.to_be_bytes());
+            b.resize(start + 88, 0);
+        }
+
+        b
+    }
+
+    #[test]
+    fn parse_stroke() {
+        let data = stroke(&[(1.5, 2.0), (10.0, 20.0)]);
+        let (rem, s) = VectorStroke::parse(&data).unwrap();
+
+        assert!(rem.is_empty());
+        assert_eq!(s.bounds, [1, 2, 30, 40]);
+        assert_eq!(s.main_color, [255, 128, 0]);
+        assert_eq!(s.opacity, 0.5);
+        assert_eq!(s.antialias, 3);
+        assert_eq!(s.brush_size, 3.0);
+        assert!(s.is_closed());
+
+        assert_eq!(s.points.len(), 2);
+        assert_eq!((s.points[1].x, s.points[1].y), (10.0, 20.0));
+        assert_eq!((s.points[0].pressure, s.points[0].width, s.points[0].opacity), (0.75, 1.0, 0.5));
+    }
+
+    #[test]
+    fn parse_strokes_until_the_data_stops() {
+        let mut data = stroke(&[(0.0, 0.0)]);
+        data.extend(stroke(&[(1.0, 1.0), (2.0, 2.0)]));
+        let (_, v) = ExtaVector::parse(&data).unwrap();
+        assert_eq!(v.strokes.len(), 2);
+
+        // a truncated stroke is left out
+        data.truncate(data.len() - 10);
+        let (_, v) = ExtaVector::parse(&data).unwrap();
+        assert_eq!(v.strokes.len(), 1);
+    }
+}
@@ -1,4 +1,19 @@
+use nom::IResult;
+use nom::number::complete::be_u32;
 
 pub mod sqli;
 pub mod head;
 pub mod exta;
+
+/// colour channels are stored as u32 with the value repeated in each byte (0xf0f0f0f0, 0x22202220), the top one is used
+pub(crate) fn color_channel(v: u32) -> u8 {
+    (v >> 24) as u8
+}
+
+/// three colour channels (see `color_channel`)
+pub(crate) fn parse_rgb(inp: &[u8]) -> IResult<&[u8], [u8; 3]> {
+    let (i, r) = be_u32(inp)?;
+    let (i, g) = be_u32(i)?;
+    let (i, b) = be_u32(i)?;
+    Ok((i, [color_channel(r), color_channel(g), color_channel(b)]))
+}
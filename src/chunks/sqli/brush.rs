@@ -0,0 +1,486 @@
+use nom::IResult;
+use nom::number::complete::be_u32;
+
+use crate::{ClipDb, LayerBlendMode, Row};
+use crate::chunks::sqli::ruler::{row_f64, row_i64};
+use crate::chunks::sqli::timeline::blob_text;
+use crate::chunks::sqli::walk_list;
+
+// the brushes used on a canvas, BrushStyleManager.FirstBrushStyle -> BrushStyle.NextIndex
+// the samples only have default brushes whose effectors are just flags, so the per input layout
+// after the flags is an assumption (see BrushEffector::parse)
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum EffectorInput {
+    Pressure,
+    Tilt,
+    Velocity,
+    Random,
+}
+
+impl EffectorInput {
+    // in flag bit order
+    pub const ALL: [EffectorInput; 4] = [EffectorInput::Pressure, EffectorInput::Tilt, EffectorInput::Velocity, EffectorInput::Random];
+
+    pub fn bit(&self) -> u32 {
+        match self {
+            EffectorInput::Pressure => 1,
+            EffectorInput::Tilt => 2,
+            EffectorInput::Velocity => 4,
+            EffectorInput::Random => 8,
+        }
+    }
+}
+
+/// the `*Effector` columns, which inputs (pressure, tilt, ...) affect a brush setting
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BrushEffector {
+    pub flags: u32,
+    pub curve_data: Vec<u8>, // ?: the curve settings of the enabled inputs, raw since no sample has any
+}
+
+impl BrushEffector {
+    // u32 BE flags (bit 0 pressure, 1 tilt, 2 velocity, 3 random; only pressure has been seen)
+    // every effector in the samples ends there, whatever follows is kept as it is
+    /// the effector blob, consumes all of it
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (i, flags) = be_u32(inp)?;
+        Ok((&i[i.len()..], BrushEffector { flags, curve_data: i.to_vec() }))
+    }
+
+    // some effectors (RotationEffector) are plain integers instead of blobs
+    fn from_row(row: &Row, column: &str) -> Self {
+        match row.get(column) {
+            Some(v) => match (v.as_bytes(), v.as_i64()) {
+                (Some(b), _) => Self::parse(b).map(|(_, e)| { e }).unwrap_or_default(),
+                (_, Some(flags)) => Self::parse(&(flags as u32).to_be_bytes()).map(|(_, e)| { e }).unwrap_or_default(),
+                _ => Self::default(),
+            },
+            None => Self::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.flags != 0
+    }
+
+    pub fn uses(&self, input: EffectorInput) -> bool {
+        self.flags & input.bit() != 0
+    }
+}
+
+/// a base value and what changes it while drawing
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct BrushParam {
+    pub base: f64,
+    pub effector: BrushEffector,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushTexture {
+    pub pattern_id: i64, // TexturePattern, 0 if none
+    pub flags: i64,
+    pub composite: i64,
+    pub scale: f64,
+    pub rotate: f64,
+    pub offset: (f64, f64),
+    pub brightness: f64,
+    pub contrast: f64,
+    pub density: BrushParam,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushWatercolor {
+    pub enabled: bool,
+    pub kind: i64, // WaterColorType
+    pub mix_color: BrushParam,
+    pub mix_alpha: BrushParam,
+    pub color_extension: f64,
+    pub blur: BrushParam,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushColorJitter {
+    pub sub_color: BrushParam,
+    pub hue: BrushParam,
+    pub saturation: BrushParam,
+    pub value: BrushParam,
+    pub target: i64, // ChangeDrawColorTarget
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushSpray {
+    pub flags: i64, // SprayFlag, 0 when spray is off
+    pub size: BrushParam,
+    pub density: BrushParam,
+    pub bias: f64,
+    pub fixed_spray_id: i64, // FixedSpray, 0 if none
+    pub rotation: BrushParam, // RotationInSprayBase, RotationEffectorInSpray
+    pub rotation_random: f64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushWaterEdge {
+    pub enabled: bool,
+    pub radius: f64,
+    pub alpha_power: f64,
+    pub value_power: f64,
+    pub blur: f64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushStyle {
+    pub id: i64,
+    pub canvas_id: i64,
+    pub flags: i64, // StyleFlag
+    pub radius: f64, // PenRadius
+    pub size: BrushEffector, // scales the radius
+    pub opacity: BrushEffector,
+    pub flow: BrushParam,
+    pub antialias: i64, // 0 none .. 3 strong?
+    pub hardness: f64,
+    pub interval: BrushParam,
+    pub auto_interval: i64, // AutoIntervalType
+    pub thickness: BrushParam,
+    pub rotation: BrushParam,
+    pub rotation_random: f64,
+    pub pattern_style_id: i64, // PatternStyle, 0 if the tip is a circle
+    pub composite_mode: LayerBlendMode, // brush only modes (erase, behind, ...) are Unknown
+    pub dual_composite_mode: i64,
+    pub texture: BrushTexture,
+    pub watercolor: BrushWatercolor,
+    pub color_jitter: BrushColorJitter,
+    pub spray: BrushSpray,
+    pub water_edge: BrushWaterEdge,
+}
+
+// {name}Base and {name}Effector
+fn row_param(row: &Row, name: &str) -> BrushParam {
+    BrushParam { base: row_f64(row, &format!("{name}Base")), effector: BrushEffector::from_row(row, &format!("{name}Effector")) }
+}
+
+impl BrushStyle {
+    /// every effector with the name of its setting, to see which settings react to the pen
+    pub fn effectors(&self) -> Vec<(&'static str, &BrushEffector)> {
+        vec![
+            ("size", &self.size),
+            ("opacity", &self.opacity),
+            ("flow", &self.flow.effector),
+            ("interval", &self.interval.effector),
+            ("thickness", &self.thickness.effector),
+            ("rotation", &self.rotation.effector),
+            ("texture_density", &self.texture.density.effector),
+            ("mix_color", &self.watercolor.mix_color.effector),
+            ("mix_alpha", &self.watercolor.mix_alpha.effector),
+            ("blur", &self.watercolor.blur.effector),
+            ("sub_color", &self.color_jitter.sub_color.effector),
+            ("hue", &self.color_jitter.hue.effector),
+            ("saturation", &self.color_jitter.saturation.effector),
+            ("value", &self.color_jitter.value.effector),
+            ("spray_size", &self.spray.size.effector),
+            ("spray_density", &self.spray.density.effector),
+            ("spray_rotation", &self.spray.rotation.effector),
+        ]
+    }
+
+    fn from_row(row: &Row) -> Self {
+        BrushStyle {
+            id: row.main_id().unwrap_or(0),
+            canvas_id: row_i64(row, "CanvasId"),
+            flags: row_i64(row, "StyleFlag"),
+            radius: row_f64(row, "PenRadius"),
+            size: BrushEffector::from_row(row, "SizeEffector"),
+            opacity: BrushEffector::from_row(row, "OpacityEffector"),
+            flow: row_param(row, "Flow"),
+            antialias: row_i64(row, "AntiAlias"),
+            hardness: row_f64(row, "Hardness"),
+            interval: row_param(row, "Interval"),
+            auto_interval: row_i64(row, "AutoIntervalType"),
+            thickness: row_param(row, "Thickness"),
+            rotation: row_param(row, "Rotation"),
+            rotation_random: row_f64(row, "RotationRandom"),
+            pattern_style_id: row_i64(row, "PatternStyle"),
+            composite_mode: LayerBlendMode::from(row_i64(row, "CompositeMode")),
+            dual_composite_mode: row_i64(row, "DualCompositeMode"),
+            texture: BrushTexture {
+                pattern_id: row_i64(row, "TexturePattern"),
+                flags: row_i64(row, "TextureFlag"),
+                composite: row_i64(row, "TextureComposite"),
+                scale: row_f64(row, "TextureScale"),
+                rotate: row_f64(row, "TextureRotate"),
+                offset: (row_f64(row, "TextureOffsetX"), row_f64(row, "TextureOffsetY")),
+                brightness: row_f64(row, "TextureBrightness"),
+                contrast: row_f64(row, "TextureContrast"),
+                density: row_param(row, "TextureDensity"),
+            },
+            watercolor: BrushWatercolor {
+                enabled: row_i64(row, "UseWaterColor") != 0,
+                kind: row_i64(row, "WaterColorType"),
+                mix_color: row_param(row, "MixColor"),
+                mix_alpha: row_param(row, "MixAlpha"),
+                color_extension: row_f64(row, "ColorExtension"),
+                blur: row_param(row, "Blur"),
+            },
+            color_jitter: BrushColorJitter {
+                sub_color: row_param(row, "SubColor"),
+                hue: row_param(row, "HueChange"),
+                saturation: row_param(row, "SaturationChange"),
+                value: row_param(row, "ValueChange"),
+                target: row_i64(row, "ChangeDrawColorTarget"),
+            },
+            spray: BrushSpray {
+                flags: row_i64(row, "SprayFlag"),
+                size: row_param(row, "SpraySize"),
+                density: row_param(row, "SprayDensity"),
+                bias: row_f64(row, "SprayBias"),
+                fixed_spray_id: row_i64(row, "FixedSpray"),
+                rotation: BrushParam {
+                    base: row_f64(row, "RotationInSprayBase"),
+                    effector: BrushEffector::from_row(row, "RotationEffectorInSpray"),
+                },
+                rotation_random: row_f64(row, "RotationRandomInSpray"),
+            },
+            water_edge: BrushWaterEdge {
+                enabled: row_i64(row, "WaterEdgeFlag") != 0,
+                radius: row_f64(row, "WaterEdgeRadius"),
+                alpha_power: row_f64(row, "WaterEdgeAlphaPower"),
+                value_power: row_f64(row, "WaterEdgeValuePower"),
+                blur: row_f64(row, "WaterEdgeBlur"),
+            },
+        }
+    }
+}
+
+/// how vector fills are drawn, linked from vector layers (`Layer.vector_fill_index`)
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct FillStyle {
+    pub id: i64,
+    pub canvas_id: i64,
+    pub flags: i64, // StyleFlag
+    pub antialias: i64,
+    pub composite_mode: LayerBlendMode, // same numbering as layers, fill only modes are Unknown
+    pub texture_density: f64,
+}
+
+impl FillStyle {
+    fn from_row(row: &Row) -> Self {
+        FillStyle {
+            id: row.main_id().unwrap_or(0),
+            canvas_id: row_i64(row, "CanvasId"),
+            flags: row_i64(row, "StyleFlag"),
+            antialias: row_i64(row, "AntiAlias"),
+            composite_mode: LayerBlendMode::from(row_i64(row, "CompositeMode")),
+            texture_density: row_f64(row, "TextureDensity"),
+        }
+    }
+}
+
+/// a brush tip or texture bitmap, the image is a mipmap like layer renders
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushPatternImage {
+    pub id: i64,
+    pub uuid: String,
+    pub name: String,
+    pub mipmap_id: i64,
+}
+
+/// the tip images of a brush (BrushStyle.PatternStyle)
+#[derive(Debug, PartialEq, Clone)]
+pub struct BrushPatternStyle {
+    pub id: i64,
+    pub image_ids: Vec<i64>, // BrushPatternImage ids
+    pub order: i64, // OrderType, how the tips are cycled through
+    pub reverse: bool,
+    pub reverse2: bool, // ?: Reverse2
+}
+
+// uuids are blobs, either the 16 raw bytes or the text
+fn row_uuid(row: &Row, column: &str) -> String {
+    match row.get(column) {
+        Some(v) => match (v.as_str(), v.as_bytes()) {
+            (Some(s), _) => s.to_string(),
+            (_, Some(b)) if b.len() == 16 => {
+                let h: String = b.iter().map(|b| { format!("{b:02x}") }).collect();
+                format!("{}-{}-{}-{}-{}", &h[..8], &h[8..12], &h[12..16], &h[16..20], &h[20..])
+            }
+            (_, Some(b)) => String::from_utf8_lossy(b).trim_end_matches('\0').to_string(),
+            _ => String::new(),
+        },
+        None => String::new(),
+    }
+}
+
+// ImageIndex: assumed to be ImageNumber u32 BE ids
+fn parse_image_ids(data: &[u8], number: usize) -> Vec<i64> {
+    data.chunks_exact(4).take(number).map(|c| { u32::from_be_bytes(c.try_into().unwrap()) as i64 }).collect()
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct BrushStyleManager {
+    pub id: i64,
+    pub canvas_id: i64,
+    // first item of each list, 0 if empty
+    pub first_graph_data: i64,
+    pub first_pattern_image: i64,
+    pub first_pattern: i64,
+    pub first_fixed_spray: i64,
+    pub first_brush_style: i64,
+    pub first_fill_style: i64,
+}
+
+impl<'a> ClipDb<'a> {
+    /// the brush style manager (`Canvas.brush_style_manager_id`)
+    pub fn get_brush_style_manager(&self, manager_id: i64) -> Option<BrushStyleManager> {
+        let row = self.row("BrushStyleManager", manager_id)?;
+
+        Some(BrushStyleManager {
+            id: manager_id,
+            canvas_id: row_i64(&row, "CanvasId"),
+            first_graph_data: row_i64(&row, "FirstGraphData"),
+            first_pattern_image: row_i64(&row, "FirstPatternImage"),
+            first_pattern: row_i64(&row, "FirstPattern"),
+            first_fixed_spray: row_i64(&row, "FirstFixedSpray"),
+            first_brush_style: row_i64(&row, "FirstBrushStyle"),
+            first_fill_style: row_i64(&row, "FirstFillStyle"),
+        })
+    }
+
+    /// rows of a brush style manager list, following NextIndex
+    pub(crate) fn get_brush_list(&self, table: &str, first_id: i64) -> Vec<Row> {
+        walk_list(first_id, |id| {
+            let row = self.row(table, id)?;
+            let next_id = row_i64(&row, "NextIndex");
+            Some((row, next_id))
+        })
+    }
+
+    /// control points of an effector graph, ControlNumber (x, y) f64 BE pairs of ControlDataSize bytes each
+    pub fn get_effector_graph(&self, graph_id: i64) -> Vec<(f64, f64)> {
+        let Some(row) = self.row("BrushEffectorGraphData", graph_id) else { return vec![] };
+        let number = row_i64(&row, "ControlNumber").max(0) as usize;
+        let size = (row_i64(&row, "ControlDataSize").max(0) as usize).max(16);
+        let data = row.get("ControlPoints").and_then(|v| { v.as_bytes() }).unwrap_or_default();
+
+        data.chunks_exact(size).take(number).map(|c| {
+            let v = |b: &[u8]| { f64::from_be_bytes(b.try_into().unwrap()) };
+            (v(&c[..8]), v(&c[8..16]))
+        }).collect()
+    }
+
+    /// the brush styles of the brush style manager in order
+    pub fn get_brush_styles(&self, manager_id: i64) -> Vec<BrushStyle> {
+        let Some(manager) = self.get_brush_style_manager(manager_id) else { return vec![] };
+        self.get_brush_list("BrushStyle", manager.first_brush_style).iter().map(BrushStyle::from_row).collect()
+    }
+
+    pub fn get_brush_style(&self, id: i64) -> Option<BrushStyle> {
+        self.row("BrushStyle", id).map(|r| { BrushStyle::from_row(&r) })
+    }
+
+    fn brush_pattern_image_from_row(row: &Row) -> BrushPatternImage {
+        let name = match row.get("Name") {
+            Some(v) => v.as_str().map(String::from).or_else(|| { v.as_bytes().map(blob_text) }).unwrap_or_default(),
+            None => String::new(),
+        };
+
+        BrushPatternImage {
+            id: row.main_id().unwrap_or(0),
+            uuid: row_uuid(row, "Uuid"),
+            name,
+            mipmap_id: row_i64(row, "Mipmap"),
+        }
+    }
+
+    pub fn get_brush_pattern_image(&self, id: i64) -> Option<BrushPatternImage> {
+        self.row("BrushPatternImage", id).as_ref().map(Self::brush_pattern_image_from_row)
+    }
+
+    /// the tip and texture images of the brush style manager in order
+    pub fn get_brush_pattern_images(&self, manager_id: i64) -> Vec<BrushPatternImage> {
+        let Some(manager) = self.get_brush_style_manager(manager_id) else { return vec![] };
+        self.get_brush_list("BrushPatternImage", manager.first_pattern_image).iter().map(Self::brush_pattern_image_from_row).collect()
+    }
+
+    fn brush_pattern_style_from_row(row: &Row) -> BrushPatternStyle {
+        let number = row_i64(row, "ImageNumber").max(0) as usize;
+        let data = row.get("ImageIndex").and_then(|v| { v.as_bytes() }).unwrap_or_default();
+
+        BrushPatternStyle {
+            id: row.main_id().unwrap_or(0),
+            image_ids: parse_image_ids(data, number),
+            order: row_i64(row, "OrderType"),
+            reverse: row_i64(row, "Reverse") != 0,
+            reverse2: row_i64(row, "Reverse2") != 0,
+        }
+    }
+
+    pub fn get_brush_pattern_style(&self, id: i64) -> Option<BrushPatternStyle> {
+        self.row("BrushPatternStyle", id).as_ref().map(Self::brush_pattern_style_from_row)
+    }
+
+    /// the tip image lists of the brush style manager in order
+    pub fn get_brush_pattern_styles(&self, manager_id: i64) -> Vec<BrushPatternStyle> {
+        let Some(manager) = self.get_brush_style_manager(manager_id) else { return vec![] };
+        self.get_brush_list("BrushPatternStyle", manager.first_pattern).iter().map(Self::brush_pattern_style_from_row).collect()
+    }
+
+    /// the fill styles of the brush style manager in order
+    pub fn get_fill_styles(&self, manager_id: i64) -> Vec<FillStyle> {
+        let Some(manager) = self.get_brush_style_manager(manager_id) else { return vec![] };
+        self.get_brush_list("FillStyle", manager.first_fill_style).iter().map(FillStyle::from_row).collect()
+    }
+
+    pub fn get_fill_style(&self, id: i64) -> Option<FillStyle> {
+        self.row("FillStyle", id).as_ref().map(FillStyle::from_row)
+    }
+
+    /// the VectorObjectList row holding a vector layer's fill (VectorNormalFillIndex), None if it has no fill
+    pub fn get_layer_fill_object(&self, layer_id: i64) -> Option<Row> {
+        let layer = self.row("Layer", layer_id)?;
+        self.follow_link(&layer, "VectorNormalFillIndex")
+    }
+
+    /// the fill style a vector layer's fill object refers to, through whichever of its columns links to FillStyle
+    /// None if the layer has no fill or the fill object has no such column (no sample has one)
+    pub fn get_layer_fill_style(&self, layer_id: i64) -> Option<FillStyle> {
+        let fill = self.get_layer_fill_object(layer_id)?;
+        let column = self.with_param_schemes(&fill.table, |schemes| {
+            schemes.iter().find(|s| { s.link() == Some("FillStyle") }).map(|s| { s.label.clone() })
+        })?;
+
+        self.follow_link(&fill, &column).as_ref().map(FillStyle::from_row)
+    }
+
+    /// the brush styles used in the canvas, empty if the file has none
+    pub fn get_brush_styles_for_canvas(&self, canvas_id: i64) -> Vec<BrushStyle> {
+        let Some(manager_id) = self.get_canvas(canvas_id).and_then(|c| { c.brush_style_manager_id }) else { return vec![] };
+        self.get_brush_styles(manager_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_flags_only_effector() {
+        let (rem, e) = BrushEffector::parse(&[0, 0, 0, 1]).unwrap();
+        assert!(rem.is_empty());
+
+        assert!(e.is_enabled());
+        assert!(e.uses(EffectorInput::Pressure));
+        assert!(!e.uses(EffectorInput::Tilt));
+        assert!(e.curve_data.is_empty());
+    }
+
+    #[test]
+    fn effector_curve_data_is_kept_raw() {
+        let (rem, e) = BrushEffector::parse(&[0, 0, 0, 5, 1, 2, 3]).unwrap();
+        assert!(rem.is_empty());
+
+        assert!(e.uses(EffectorInput::Pressure) && e.uses(EffectorInput::Velocity));
+        assert_eq!(e.curve_data, vec![1, 2, 3]);
+
+        assert!(BrushEffector::parse(&[0, 0]).is_err());
+    }
+}
@@ -1,22 +1,25 @@
-use num_enum::TryFromPrimitive;
-use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
-use crate::ClipDb;
+use num_enum::FromPrimitive;
+use rusqlite::types::{FromSql, FromSqlResult, ValueRef};
+use crate::{ClipDb, RgbaImage};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Canvas {
     pub id: i64,
     pub unit: CanvasUnit,
-    pub width: f64,
+    pub width: f64, // always in pixels, unit is only what CSP displays
     pub height: f64,
     pub resolution_dpi: f64,
-    // channel_bytes: i64,
-    // default_channel_order: i64,
-    // root_folder_id: i64,
-    pub current_layer_id: i64,
+    pub channel_bytes: Option<i64>,
+    pub default_channel_order: Option<i64>,
+    pub root_folder_id: i64,
+    pub current_layer_id: i64, // 0 in files without CanvasCurrentLayer
+    // only in files that use them
+    pub brush_style_manager_id: Option<i64>,
+    pub model_3d_loader_index: Option<i64>,
     // there's more but idk what they mean yet
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, FromPrimitive)]
 #[repr(i64)]
 pub enum CanvasUnit {
     Pixels = 0,
@@ -28,20 +31,194 @@ pub enum CanvasUnit {
     Unknown(i64),
 } // why is there no 4??
 
+impl CanvasUnit {
+    // how many of the unit make an inch, pixels depend on the resolution
+    fn per_inch(&self, dpi: f64) -> Option<f64> {
+        match self {
+            CanvasUnit::Pixels => Some(dpi),
+            CanvasUnit::Centimetres => Some(2.54),
+            CanvasUnit::Millimetres => Some(25.4),
+            CanvasUnit::Inches => Some(1.0),
+            CanvasUnit::Points => Some(72.0),
+            CanvasUnit::Unknown(_) => None,
+        }
+    }
+
+    /// converts a length in this unit to another unit, `dpi` is used for pixels
+    pub fn convert(&self, value: f64, to: CanvasUnit, dpi: f64) -> Option<f64> {
+        Some(value / self.per_inch(dpi)? * to.per_inch(dpi)?)
+    }
+}
+
+impl Canvas {
+    /// size in whole pixels
+    pub fn size_px(&self) -> (u32, u32) {
+        (self.width.round() as u32, self.height.round() as u32)
+    }
+
+    /// size in the given unit at the canvas resolution
+    pub fn size_in(&self, unit: CanvasUnit) -> Option<(f64, f64)> {
+        let convert = |v: f64| { CanvasUnit::Pixels.convert(v, unit, self.resolution_dpi) };
+        Some((convert(self.width)?, convert(self.height)?))
+    }
+}
+
 impl FromSql for CanvasUnit {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        CanvasUnit::try_from(value.as_i64()?).map_err(|_| { FromSqlError::InvalidType })
+        Ok(CanvasUnit::from(value.as_i64()?))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, FromPrimitive)]
+#[repr(i64)]
+pub enum RenderingIntent {
+    // assumed to use the icc numbering
+    Perceptual = 0,
+    RelativeColorimetric = 1,
+    Saturation = 2,
+    AbsoluteColorimetric = 3,
+    #[num_enum(catch_all)]
+    Unknown(i64),
+}
+
+impl FromSql for RenderingIntent {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(RenderingIntent::from(value.as_i64()?))
+    }
+}
+
+/// an embedded icc profile
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct IccProfile {
+    pub name: Option<String>,
+    pub data: Vec<u8>,
+}
+
+/// colour management settings of a canvas, every field is empty when the canvas isn't colour managed
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CanvasColorSettings {
+    pub src_profile: Option<IccProfile>, // the working space of the canvas
+    pub dst_profile: Option<IccProfile>,
+    pub rendering_intent: Option<RenderingIntent>,
+    pub simulate: bool, // CanvasDoSimulateColor (soft proofing)
+    pub simulate_src_profile: Option<IccProfile>,
+    pub simulate_dst_profile: Option<IccProfile>,
+    pub simulate_rendering_intent: Option<RenderingIntent>,
+    // ?: CanvasUseLibraryType, CanvasSimulateUseLibraryType
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum PreviewFormat {
+    Png,
+    // raw pixels are a guess based on the data size, only png (ImageType 1) has been seen
+    Bgra,
+    Bgr,
+    Unknown,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CanvasPreview {
+    pub canvas_id: i64,
+    pub image_type: i64, // 1 = png
+    pub width: u32,
+    pub height: u32,
+    pub format: PreviewFormat,
+    pub data: Vec<u8>,
+}
+
+impl CanvasPreview {
+    fn detect_format(image_type: i64, width: u32, height: u32, data: &[u8]) -> PreviewFormat {
+        let pixels = width as usize * height as usize;
+
+        if image_type == 1 || data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            PreviewFormat::Png
+        } else if data.len() == pixels * 4 {
+            PreviewFormat::Bgra
+        } else if data.len() == pixels * 3 {
+            PreviewFormat::Bgr
+        } else {
+            PreviewFormat::Unknown
+        }
+    }
+
+    /// the preview as an rgba image regardless of the stored format
+    pub fn to_rgba(&self) -> Option<RgbaImage> {
+        let data = match self.format {
+            PreviewFormat::Png => return RgbaImage::from_png(&self.data),
+            PreviewFormat::Bgra => self.data.chunks_exact(4).flat_map(|p| { [p[2], p[1], p[0], p[3]] }).collect(),
+            PreviewFormat::Bgr => self.data.chunks_exact(3).flat_map(|p| { [p[2], p[1], p[0], 255] }).collect(),
+            PreviewFormat::Unknown => return None,
+        };
+
+        Some(RgbaImage { width: self.width, height: self.height, data })
     }
 }
 
 impl<'a> ClipDb<'a> {
-    /// The raw image preview data for the given canvas
-    pub fn get_preview_image_for_canvas(&self, canvas_id: i64) -> Option<Vec<u8>> {
-        let stmt = self.conn().prepare_cached("SELECT ImageData from CanvasPreview where CanvasId=?1");
+    /// the image preview for the given canvas
+    pub fn get_preview_image_for_canvas(&self, canvas_id: i64) -> Option<CanvasPreview> {
+        let stmt = self.conn().prepare_cached("SELECT ImageType, ImageWidth, ImageHeight, ImageData from CanvasPreview where CanvasId=?1");
+
+        stmt.unwrap().query_row([canvas_id], |r| {
+            let image_type = r.get(0)?;
+            let width = r.get(1)?;
+            let height = r.get(2)?;
+            let data: Vec<u8> = r.get(3)?;
+
+            Ok(CanvasPreview {
+                canvas_id,
+                image_type,
+                width,
+                height,
+                format: CanvasPreview::detect_format(image_type, width, height, &data),
+                data,
+            })
+        }).ok()
+    }
+
+    /// colour profiles and rendering intents of the given canvas
+    pub fn get_canvas_color_settings(&self, canvas_id: i64) -> Option<CanvasColorSettings> {
+        if !self.column_exists("Canvas", "CanvasSrcProfile") {
+            return None;
+        }
+
+        let stmt = self.conn().prepare_cached("SELECT \
+                CanvasSrcProfileName, \
+                CanvasSrcProfile, \
+                CanvasDstProfileName, \
+                CanvasDstProfile, \
+                CanvasRenderingIntent, \
+                CanvasDoSimulateColor, \
+                CanvasSimulateSrcProfileName, \
+                CanvasSimulateSrcProfile, \
+                CanvasSimulateDstProfileName, \
+                CanvasSimulateDstProfile, \
+                CanvasSimulateRenderingIntent \
+            FROM Canvas WHERE MainId=?1");
 
         stmt.unwrap().query_row([canvas_id], |r| {
-            let v = r.get(0).unwrap();
-            Ok(v)
+            // names might be stored as text or blobs
+            let name = |i: usize| -> Option<String> {
+                match r.get_ref(i).ok()? {
+                    ValueRef::Text(t) | ValueRef::Blob(t) => Some(String::from_utf8_lossy(t).trim_end_matches('\0').to_string()),
+                    _ => None,
+                }
+            };
+
+            let profile = |i: usize| -> rusqlite::Result<Option<IccProfile>> {
+                let data: Option<Vec<u8>> = r.get(i + 1)?;
+                Ok(data.filter(|d| { !d.is_empty() }).map(|data| { IccProfile { name: name(i), data } }))
+            };
+
+            Ok(CanvasColorSettings {
+                src_profile: profile(0)?,
+                dst_profile: profile(2)?,
+                rendering_intent: r.get(4)?,
+                simulate: r.get::<_, Option<i64>>(5)?.unwrap_or(0) != 0,
+                simulate_src_profile: profile(6)?,
+                simulate_dst_profile: profile(8)?,
+                simulate_rendering_intent: r.get(10)?,
+            })
         }).ok()
     }
 
@@ -50,30 +227,48 @@ impl<'a> ClipDb<'a> {
         let stmt = self.conn().prepare_cached("SELECT MainId from Canvas");
 
         stmt.unwrap().query_map([], |r| {
-            Ok(r.get(0)?)
+            r.get(0)
         }).unwrap().map(|r| { r.unwrap() }).collect()
     }
 
     /// get the canvas for the given canvas ID
     pub fn get_canvas(&self, canvas_id: i64) -> Option<Canvas> {
-        let stmt = self.conn().prepare_cached("SELECT \
+        let optional = |column: &str| { self.column_or_null("Canvas", column) };
+
+        let stmt = self.conn().prepare_cached(&format!("SELECT \
                 MainId, \
                 CanvasUnit, \
                 CanvasWidth, \
                 CanvasHeight, \
                 CanvasResolution, \
-                CanvasCurrentLayer \
-            FROM Canvas WHERE MainId=?1");
+                CanvasRootFolder, \
+                {}, \
+                {}, \
+                {}, \
+                {}, \
+                {} \
+            FROM Canvas WHERE MainId=?1",
+            optional("CanvasChannelBytes"),
+            optional("CanvasDefaultChannelOrder"),
+            optional("CanvasCurrentLayer"),
+            optional("BrushStyleManager"),
+            optional("Canvas3DModelDataLoaderIndex"),
+        ));
 
-        stmt.unwrap().query_row([canvas_id], |r| {
+        stmt.ok()?.query_row([canvas_id], |r| {
             Ok(Canvas {
-                id: r.get(0).unwrap(),
-                unit: r.get(1).unwrap(),
-                width: r.get(2).unwrap(),
-                height: r.get(3).unwrap(),
-                resolution_dpi: r.get(4).unwrap(),
-                current_layer_id: r.get(5).unwrap(),
+                id: r.get(0)?,
+                unit: r.get::<_, Option<CanvasUnit>>(1)?.unwrap_or(CanvasUnit::Pixels),
+                width: r.get(2)?,
+                height: r.get(3)?,
+                resolution_dpi: r.get(4)?,
+                root_folder_id: r.get(5)?,
+                channel_bytes: r.get(6)?,
+                default_channel_order: r.get(7)?,
+                current_layer_id: r.get::<_, Option<i64>>(8)?.unwrap_or(0),
+                brush_style_manager_id: r.get::<_, Option<i64>>(9)?.filter(|id| { *id != 0 }),
+                model_3d_loader_index: r.get(10)?,
             })
         }).ok()
     }
-}
\ No newline at end of file
+}
@@ -1,10 +1,13 @@
 use nom::bytes::complete::take;
+use nom::combinator::{map, verify};
 use nom::IResult;
-use nom::number::complete::{be_i32, be_u32};
-use num_enum::TryFromPrimitive;
+use nom::multi::count;
+use nom::number::complete::{be_f64, be_i32, be_u16, be_u32};
+use num_enum::FromPrimitive;
 use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
 
 use crate::ClipDb;
+use crate::chunks::{color_channel, parse_rgb};
 
 // a lot of these actually have smaller possible values
 // we're just going with the largest that would fit for consistency
@@ -80,12 +83,12 @@ impl FilterLayerInfo {
 
 impl FromSql for FilterLayerInfo {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        let (_, v) = Self::parse(value.as_bytes()?).unwrap();
+        let (_, v) = Self::parse(value.as_bytes()?).map_err(|e| { FromSqlError::Other(e.to_owned().into()) })?;
         Ok(v)
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, FromPrimitive)]
 #[repr(i64)]
 pub enum LayerBlendMode {
     Normal = 0,
@@ -122,11 +125,11 @@ pub enum LayerBlendMode {
 
 impl FromSql for LayerBlendMode {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        LayerBlendMode::try_from(value.as_i64()?).map_err(|_| { FromSqlError::InvalidType })
+        Ok(LayerBlendMode::from(value.as_i64()?))
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, TryFromPrimitive)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, FromPrimitive)]
 #[repr(i64)]
 pub enum LayerKind {
     Dummy = 256, // root folder
@@ -141,7 +144,101 @@ pub enum LayerKind {
 
 impl FromSql for LayerKind {
     fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
-        LayerKind::try_from(value.as_i64()?).map_err(|_| { FromSqlError::InvalidType })
+        Ok(LayerKind::from(value.as_i64()?))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum LayerEffect {
+    // LayerEffectInfo
+    // size: u32
+    // ?: u32 = 2 (the first 2 effects have no size prefix)
+    // [name: utf16 block tag, data]
+    Border { enabled: bool, width: f64, color: [u8; 3] }, // EffectEdge
+    Tone { enabled: bool }, // EffectTone todo
+    WaterEdge { enabled: bool, range: f64, opacity: f64, darkness: f64, blur: f64 }, // EffectWaterEdge
+    LineExtraction { enabled: bool }, // EffectLine todo
+    Unknown(String), // EffectTextureMap, EffectApplyOpacity, EffectToneAreaColor, EffectTonePosterize
+}
+
+impl LayerEffect {
+    fn parse_name(inp: &[u8]) -> IResult<&[u8], String> {
+        let (i, len) = be_u32(inp)?;
+        let (i, chars) = count(be_u16, len as usize)(i)?;
+        Ok((i, String::from_utf16_lossy(&chars)))
+    }
+
+    fn parse_enabled(inp: &[u8]) -> IResult<&[u8], bool> {
+        map(verify(be_u32, |x| { *x == 0 || *x == 1 }), |x| { x == 1 })(inp)
+    }
+
+    fn parse_border(data: &[u8]) -> IResult<&[u8], Self> {
+        let (i, enabled) = Self::parse_enabled(data)?;
+        let (i, width) = be_f64(i)?;
+        let (i, color) = parse_rgb(i)?;
+        Ok((i, LayerEffect::Border { enabled, width, color }))
+    }
+
+    fn parse_tone(data: &[u8]) -> IResult<&[u8], Self> {
+        let (i, enabled) = Self::parse_enabled(data)?;
+        let (i, _) = take(72u32)(i)?;
+        Ok((i, LayerEffect::Tone { enabled }))
+    }
+
+    fn parse_water_edge(data: &[u8]) -> IResult<&[u8], Self> {
+        let (i, enabled) = Self::parse_enabled(data)?;
+        // order guessed from the effect dialog
+        let (i, range) = be_f64(i)?;
+        let (i, opacity) = be_f64(i)?;
+        let (i, darkness) = be_f64(i)?;
+        let (i, blur) = be_f64(i)?;
+        Ok((i, LayerEffect::WaterEdge { enabled, range, opacity, darkness, blur }))
+    }
+
+    fn parse_line_extraction(data: &[u8]) -> IResult<&[u8], Self> {
+        let (i, enabled) = Self::parse_enabled(data)?;
+        Ok((i, LayerEffect::LineExtraction { enabled }))
+    }
+
+    /// parse a single effect (name + data)
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (i, name) = Self::parse_name(inp)?;
+
+        // these don't have a size prefix
+        match name.as_str() {
+            "EffectEdge" => return Self::parse_border(i),
+            "EffectTone" => return Self::parse_tone(i),
+            _ => {}
+        }
+
+        let (_, size) = verify(be_u32, |x| { *x >= 4 })(i)?;
+        let (i, data) = take(size)(i)?;
+        let (data, _) = be_u32(data)?; // size includes itself
+
+        let effect = match name.as_str() {
+            "EffectWaterEdge" => Self::parse_water_edge(data)?.1,
+            "EffectLine" => Self::parse_line_extraction(data)?.1,
+            _ => LayerEffect::Unknown(name),
+        };
+
+        Ok((i, effect))
+    }
+
+    /// parse from layer effect info
+    pub fn parse_list(inp: &[u8]) -> IResult<&[u8], Vec<Self>> {
+        let (_, size) = be_u32(inp)?;
+        let (remaining, info) = take(size)(inp)?;
+        let (mut i, _) = take(8u32)(info)?;
+
+        let mut effects = vec![];
+
+        // there's some trailing data after the last effect
+        while let Ok((rem, effect)) = Self::parse(i) {
+            effects.push(effect);
+            i = rem;
+        }
+
+        Ok((remaining, effects))
     }
 }
 
@@ -153,6 +250,33 @@ pub struct Layer {
     pub kind: LayerKind,
     pub alpha: i64, // this actually only goes to 256
     pub blend_mode: LayerBlendMode,
+    pub visibility: i64, // bit 0: visible, bit 1: layer mask enabled
+    pub folder: i64, // 0 for non folders (1 for root, 17 for folders)
+    pub clip: bool, // clip to layer below
+    pub offset_x: i64,
+    pub offset_y: i64,
+    pub next_id: i64, // next layer above this one in the same folder, 0 if none
+    pub first_child_id: i64, // bottom-most child for folders, 0 if none
+    // only in files that use them
+    pub special_ruler_manager_id: Option<i64>,
+    pub manager_3d_id: Option<i64>,
+    pub uuid: Option<String>,
+    pub animation_folder: bool, // folder whose children are the cels of an animation track
+    pub vector_fill_index: Option<i64>, // VectorNormalFillIndex, VectorObjectList MainId of the layer's fill, 0 for none
+}
+
+impl Layer {
+    pub fn is_visible(&self) -> bool {
+        self.visibility & 1 != 0
+    }
+
+    pub fn is_mask_enabled(&self) -> bool {
+        self.visibility & 2 != 0
+    }
+
+    pub fn is_folder(&self) -> bool {
+        self.folder != 0
+    }
 }
 
 impl<'a> ClipDb<'a> {
@@ -169,7 +293,7 @@ impl<'a> ClipDb<'a> {
         ));
 
         stmt.unwrap().query_map([layer_id], |r| {
-            Ok(r.get(0)?)
+            r.get(0)
         }).unwrap().map(|r| { r.unwrap() }).collect()
     }
 
@@ -177,43 +301,113 @@ impl<'a> ClipDb<'a> {
         self.get_ext_id_offsets_for_layer("Offscreen", "BlockData", layer_id)
     }
 
+    pub fn get_vector_exta_offsets(&self, layer_id: i64) -> Vec<i64> {
+        self.get_ext_id_offsets_for_layer("VectorObjectList", "VectorData", layer_id)
+    }
+
     /// gets layers in the canvas with the given canvas ID
     pub fn get_layer_ids_for_canvas(&self, canvas_id: i64) -> Vec<i64> {
         let stmt = self.conn.prepare_cached("SELECT MainId FROM Layer WHERE CanvasId=?1");
 
         stmt.unwrap().query_map([canvas_id], |r| {
-            Ok(r.get(0)?)
+            r.get(0)
         }).unwrap().map(|r| { r.unwrap() }).collect()
     }
 
     /// gets the layer for the given ID if any
     pub fn get_layer(&self, layer_id: i64) -> Option<Layer> {
-        let stmt = self.conn().prepare_cached("SELECT \
+        let stmt = self.conn().prepare_cached(&format!("SELECT \
                 MainId, \
                 CanvasId, \
                 LayerName, \
                 LayerType, \
                 LayerOpacity,\
-                LayerComposite \
-            FROM Layer WHERE MainId=?1");
+                LayerComposite, \
+                LayerVisibility, \
+                LayerFolder, \
+                LayerClip, \
+                LayerOffsetX, \
+                LayerOffsetY, \
+                LayerNextIndex, \
+                LayerFirstChildIndex, \
+                {}, \
+                {}, \
+                {}, \
+                {}, \
+                {} \
+            FROM Layer WHERE MainId=?1",
+            self.column_or_null("Layer", "SpecialRulerManager"),
+            self.column_or_null("Layer", "Manager3DOd"),
+            self.column_or_null("Layer", "LayerUuid"),
+            self.column_or_null("Layer", "AnimationFolder"),
+            self.column_or_null("Layer", "VectorNormalFillIndex"),
+        ));
 
-        stmt.unwrap().query_row([layer_id], |r| {
+        stmt.ok()?.query_row([layer_id], |r| {
             Ok(Layer {
-                id: r.get(0).unwrap(),
-                canvas_id: r.get(1).unwrap(),
-                name: r.get(2).unwrap(),
-                kind: r.get(3).unwrap(),
-                alpha: r.get(4).unwrap(),
-                blend_mode: r.get(5).unwrap(),
+                id: r.get(0)?,
+                canvas_id: r.get(1)?,
+                name: r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                kind: r.get(3)?,
+                alpha: r.get(4)?,
+                blend_mode: r.get(5)?,
+                visibility: r.get(6)?,
+                folder: r.get(7)?,
+                clip: r.get::<_, i64>(8)? != 0,
+                offset_x: r.get(9)?,
+                offset_y: r.get(10)?,
+                next_id: r.get(11)?,
+                first_child_id: r.get(12)?,
+                special_ruler_manager_id: r.get::<_, Option<i64>>(13)?.filter(|id| { *id != 0 }),
+                manager_3d_id: r.get::<_, Option<i64>>(14)?.filter(|id| { *id != 0 }),
+                uuid: r.get::<_, Option<String>>(15)?.filter(|u| { !u.is_empty() }),
+                animation_folder: r.get::<_, Option<i64>>(16)?.unwrap_or(0) != 0,
+                vector_fill_index: r.get(17)?,
             })
         }).ok()
     }
 
     pub fn get_fiter_layer_info(&self, layer_id: i64) -> Option<FilterLayerInfo> {
+        if !self.column_exists("Layer", "FilterLayerInfo") {
+            return None;
+        }
+
         let stmt = self.conn().prepare_cached("SELECT FilterLayerInfo FROM Layer WHERE MainId=?1");
         stmt.unwrap().query_row([layer_id], |r| {
             let f: FilterLayerInfo = r.get(0)?;
             Ok(f)
         }).ok()
     }
+
+    /// the layer effects (border, watercolour edge, etc.) of the given layer, empty if there are none
+    pub fn get_layer_effects(&self, layer_id: i64) -> Vec<LayerEffect> {
+        if !self.column_exists("Layer", "LayerEffectInfo") {
+            return vec![];
+        }
+
+        let stmt = self.conn().prepare_cached("SELECT LayerEffectInfo FROM Layer WHERE MainId=?1");
+
+        let info: Option<Vec<u8>> = stmt.unwrap().query_row([layer_id], |r| {
+            r.get(0)
+        }).ok().flatten();
+
+        info.and_then(|b| { LayerEffect::parse_list(&b).ok().map(|(_, v)| { v }) }).unwrap_or_default()
+    }
+
+    /// the draw colour of the given layer (the paper colour for paper layers) if it's enabled
+    pub fn get_layer_draw_color(&self, layer_id: i64) -> Option<[u8; 3]> {
+        let stmt = self.conn().prepare_cached("SELECT \
+                DrawColorEnable, \
+                DrawColorMainRed, \
+                DrawColorMainGreen, \
+                DrawColorMainBlue \
+            FROM Layer WHERE MainId=?1");
+
+        stmt.unwrap().query_row([layer_id], |r| {
+            let enabled: Option<i64> = r.get(0)?;
+            let c = |idx: usize| -> rusqlite::Result<u8> { Ok(color_channel(r.get::<_, i64>(idx)? as u32)) };
+
+            Ok((enabled == Some(1)).then_some([c(1)?, c(2)?, c(3)?]))
+        }).ok().flatten()
+    }
 }
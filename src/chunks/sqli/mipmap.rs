@@ -0,0 +1,74 @@
+use crate::ClipDb;
+use crate::chunks::sqli::walk_list;
+
+/// a single stored scale of a mipmap
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct MipmapLevel {
+    pub id: i64,
+    pub scale: f64, // 1.0 is full size (ThisScale is stored as a percentage)
+    pub offscreen_id: i64,
+}
+
+/// the pre-scaled offscreens CSP keeps for a layer, largest first
+#[derive(Debug, PartialEq, Clone)]
+pub struct MipmapChain {
+    pub id: i64,
+    pub levels: Vec<MipmapLevel>,
+}
+
+impl MipmapChain {
+    /// full size level
+    pub fn base(&self) -> Option<&MipmapLevel> {
+        self.levels.first()
+    }
+
+    /// the smallest level that is still at least the given scale, the base level if the scale is above all of them
+    pub fn nearest(&self, scale: f64) -> Option<&MipmapLevel> {
+        self.levels.iter()
+            .filter(|l| { l.scale >= scale })
+            .min_by(|a, b| { a.scale.total_cmp(&b.scale) })
+            .or_else(|| { self.levels.iter().max_by(|a, b| { a.scale.total_cmp(&b.scale) }) })
+    }
+}
+
+impl<'a> ClipDb<'a> {
+    fn get_mipmap_info(&self, info_id: i64) -> Option<(MipmapLevel, i64)> {
+        let stmt = self.conn().prepare_cached("SELECT MainId, ThisScale, Offscreen, NextIndex FROM MipmapInfo WHERE MainId=?1");
+
+        stmt.unwrap().query_row([info_id], |r| {
+            let level = MipmapLevel {
+                id: r.get(0)?,
+                scale: r.get::<_, f64>(1)? / 100.0,
+                offscreen_id: r.get(2)?,
+            };
+
+            Ok((level, r.get(3)?))
+        }).ok()
+    }
+
+    /// all levels of the mipmap with the given ID, following NextIndex from the base level
+    pub fn get_mipmap_chain(&self, mipmap_id: i64) -> Option<MipmapChain> {
+        let stmt = self.conn().prepare_cached("SELECT BaseMipmapInfo FROM Mipmap WHERE MainId=?1");
+        let base: i64 = stmt.unwrap().query_row([mipmap_id], |r| { r.get(0) }).ok()?;
+
+        let levels = walk_list(base, |id| { self.get_mipmap_info(id) });
+        Some(MipmapChain { id: mipmap_id, levels })
+    }
+
+    fn get_layer_mipmap_chain(&self, mipmap_column: &str, layer_id: i64) -> Option<MipmapChain> {
+        let stmt = self.conn().prepare_cached(&format!("SELECT {mipmap_column} FROM Layer WHERE MainId=?1"));
+        let mipmap_id: i64 = stmt.unwrap().query_row([layer_id], |r| { r.get(0) }).ok()?;
+
+        self.get_mipmap_chain(mipmap_id)
+    }
+
+    /// mipmap of the rendered layer
+    pub fn get_layer_render_mipmap(&self, layer_id: i64) -> Option<MipmapChain> {
+        self.get_layer_mipmap_chain("LayerRenderMipmap", layer_id)
+    }
+
+    /// mipmap of the layer mask if the layer has one
+    pub fn get_layer_mask_mipmap(&self, layer_id: i64) -> Option<MipmapChain> {
+        self.get_layer_mipmap_chain("LayerLayerMaskMipmap", layer_id)
+    }
+}
@@ -1,10 +1,27 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
 use nom::bytes::complete::{tag, take};
 use nom::IResult;
 use nom::number::complete::be_u64;
 use rusqlite::Connection;
 
-mod canvas;
-mod layer;
+use crate::ParamScheme;
+
+pub(crate) mod brush;
+pub(crate) mod canvas;
+pub(crate) mod layer;
+pub(crate) mod mipmap;
+pub(crate) mod offscreen;
+pub(crate) mod project;
+pub(crate) mod ruler;
+pub(crate) mod scene3d;
+pub(crate) mod schema;
+pub(crate) mod scheme;
+pub(crate) mod thumbnail;
+pub(crate) mod timelapse;
+pub(crate) mod timeline;
 
 pub struct ClipSqliteChunk {
     size: u64,
@@ -29,14 +46,76 @@ impl ClipSqliteChunk {
 }
 
 
+/// follows a linked list of rows (next/first index columns) from `first`, `next` returns the item with the given id
+/// and the id after it. stops at 0, at an id that was already visited (so cycles end) or when `next` returns None
+pub(crate) fn walk_list<T>(first: i64, mut next: impl FnMut(i64) -> Option<(T, i64)>) -> Vec<T> {
+    let mut items = vec![];
+    let mut seen = HashSet::new();
+    let mut id = first;
+
+    while id != 0 && seen.insert(id) {
+        let Some((item, next_id)) = next(id) else { break };
+        items.push(item);
+        id = next_id;
+    }
+
+    items
+}
+
+/// column names and ParamSchemes of each table looked up so far, the schema of a file never changes so they're only queried once
+/// keep one per connection and make every ClipDb for it with `ClipDb::with_cache`
+#[derive(Debug, Default, Clone)]
+pub struct SchemaCache {
+    tables: RefCell<HashMap<String, Vec<String>>>, // empty if the table doesn't exist
+    schemes: RefCell<HashMap<String, Vec<ParamScheme>>>,
+}
+
+impl SchemaCache {
+    fn with_columns<T>(&self, conn: &Connection, table: &str, f: impl FnOnce(&[String]) -> T) -> T {
+        if let Some(columns) = self.tables.borrow().get(table) {
+            return f(columns);
+        }
+
+        let columns: Vec<String> = conn.prepare_cached("SELECT name FROM pragma_table_info(?1)").ok().map_or(vec![], |mut stmt| {
+            stmt.query_map([table], |r| { r.get(0) })
+                .map(|rows| { rows.filter_map(|r| { r.ok() }).collect() })
+                .unwrap_or_default()
+        });
+
+        let result = f(&columns);
+        self.tables.borrow_mut().insert(table.to_string(), columns);
+        result
+    }
+
+    fn with_param_schemes<T>(&self, conn: &Connection, table: &str, f: impl FnOnce(&[ParamScheme]) -> T) -> T {
+        if let Some(schemes) = self.schemes.borrow().get(table) {
+            return f(schemes);
+        }
+
+        let schemes = scheme::load_param_schemes(conn, table);
+
+        let result = f(&schemes);
+        self.schemes.borrow_mut().insert(table.to_string(), schemes);
+        result
+    }
+}
+
 // db wrapper for csp
 pub struct ClipDb<'a> {
     conn: &'a Connection,
+    schema: Cow<'a, SchemaCache>,
 }
 
 impl<'a> ClipDb<'a> {
+    /// a ClipDb with a schema cache of its own, which starts out empty so every ClipDb made this way queries
+    /// the columns and ParamSchemes it needs again, use `with_cache` when making more than one for the connection
     pub fn with_conn(conn: &'a Connection) -> Self {
-        Self { conn }
+        Self { conn, schema: Cow::Owned(SchemaCache::default()) }
+    }
+
+    /// a ClipDb sharing the given schema cache, which has to stay with the one connection
+    pub fn with_cache(conn: &'a Connection, schema: &'a SchemaCache) -> Self {
+        Self { conn, schema: Cow::Borrowed(schema) }
     }
 }
 
@@ -56,8 +135,29 @@ impl<'a> ClipDb<'a> {
 
     /// checks whether a table exists
     pub fn table_exists(&self, name: &str) -> bool {
-        let stmt = self.conn.prepare_cached("SELECT name FROM sqlite_master WHERE type='table' AND name=?1");
-        stmt.unwrap().exists([name]).unwrap()
+        self.schema.with_columns(self.conn, name, |c| { !c.is_empty() })
+    }
+
+    /// checks whether a column exists in the given table
+    pub fn column_exists(&self, table: &str, column: &str) -> bool {
+        self.schema.with_columns(self.conn, table, |c| { c.iter().any(|c| { c == column }) })
+    }
+
+    pub(crate) fn with_param_schemes<T>(&self, table: &str, f: impl FnOnce(&[ParamScheme]) -> T) -> T {
+        self.schema.with_param_schemes(self.conn, table, f)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_list_stops_at_zero_cycles_and_missing_rows() {
+        let next = [0, 2, 3, 0, 1];
+        assert_eq!(walk_list(1, |id| { Some((id, next[id as usize])) }), vec![1, 2, 3]);
+        assert_eq!(walk_list(4, |id| { Some((id, [0, 2, 4, 0, 1][id as usize])) }), vec![4, 1, 2]);
+        assert_eq!(walk_list(1, |id| { (id < 3).then_some((id, next[id as usize])) }), vec![1, 2]);
+        assert!(walk_list(0, |id| { Some((id, 1)) }).is_empty());
+    }
+}
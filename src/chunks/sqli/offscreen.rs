@@ -0,0 +1,143 @@
+use std::sync::LazyLock;
+
+use nom::bytes::complete::{tag, take};
+use nom::combinator::verify;
+use nom::IResult;
+use nom::multi::count;
+use nom::number::complete::be_u32;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+
+use crate::chunks::color_channel;
+use crate::chunks::exta::offscreen::tags::blockdata_tag;
+use crate::ClipDb;
+
+static PARAMETER: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("Parameter") });
+static INIT_COLOR: LazyLock<Vec<u8>> = LazyLock::new(|| { blockdata_tag("InitColor") });
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct OffscreenAttribute {
+    // ?: u32 = 16 (header size)
+    // parameter_size: u32
+    // init_color_size: u32
+    // block_size_size: u32
+    //---
+    // Parameter : block tag
+    pub width: u32,
+    pub height: u32,
+    pub block_columns: u32,
+    pub block_rows: u32,
+    // ???: [u32; 16] channel layout, differs between colour and mask offscreens
+    //---
+    // InitColor : block tag
+    // ?: u32 = 20
+    // init_flag: u32 == 0 || 1
+    // alpha: u32
+    // channel_count: u32 (4 for colour, 0 for masks)
+    // ?: u32 = 4
+    // channels: [u32; channel_count] BGRA
+    pub init_color: Option<[u8; 4]>, //< rgba that empty blocks are filled with
+    //---
+    // BlockSize : block tag
+    // ?: u32 = 12
+    // block_count: u32
+    // ?: u32 = 4
+    // compressed block sizes: [u32; block_count]
+}
+
+impl OffscreenAttribute {
+    fn parse_parameter(inp: &[u8]) -> IResult<&[u8], (u32, u32, u32, u32)> {
+        let (i, _) = tag(PARAMETER.as_slice())(inp)?;
+
+        let (i, width) = be_u32(i)?;
+        let (i, height) = be_u32(i)?;
+        let (i, cols) = be_u32(i)?;
+        let (i, rows) = be_u32(i)?;
+
+        Ok((i, (width, height, cols, rows)))
+    }
+
+    fn parse_init_color(inp: &[u8]) -> IResult<&[u8], Option<[u8; 4]>> {
+        let (i, _) = tag(INIT_COLOR.as_slice())(inp)?;
+
+        let (i, _) = be_u32(i)?;
+        let (i, flag) = verify(be_u32, |x| { *x == 0 || *x == 1 })(i)?;
+        let (i, alpha) = be_u32(i)?;
+        let (i, channel_count) = be_u32(i)?;
+        let (i, _) = be_u32(i)?;
+        let (i, channels) = count(be_u32, channel_count as usize)(i)?;
+
+        let c = |idx: usize| { channels.get(idx).map_or(0, |v| { color_channel(*v) }) };
+
+        Ok((i, (flag == 1).then(|| { [c(2), c(1), c(0), color_channel(alpha)] })))
+    }
+
+    pub fn parse(inp: &[u8]) -> IResult<&[u8], Self> {
+        let (i, _) = verify(be_u32, |x| { *x == 16 })(inp)?;
+        let (i, param_size) = be_u32(i)?;
+        let (i, init_size) = be_u32(i)?;
+        let (i, block_size_size) = be_u32(i)?;
+
+        let (i, param) = take(param_size)(i)?;
+        let (i, init) = take(init_size)(i)?;
+        let (i, _) = take(block_size_size)(i)?;
+
+        let (_, (width, height, block_columns, block_rows)) = Self::parse_parameter(param)?;
+        let (_, init_color) = Self::parse_init_color(init)?;
+
+        Ok((i, OffscreenAttribute { width, height, block_columns, block_rows, init_color }))
+    }
+}
+
+impl FromSql for OffscreenAttribute {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Self::parse(value.as_blob()?).map(|(_, v)| { v }).map_err(|_| { FromSqlError::InvalidType })
+    }
+}
+
+impl<'a> ClipDb<'a> {
+    /// the attribute (dimensions, fill colour) of the offscreen with the given ID
+    pub fn get_offscreen_attribute(&self, offscreen_id: i64) -> Option<OffscreenAttribute> {
+        let stmt = self.conn().prepare_cached("SELECT Attribute FROM Offscreen WHERE MainId=?1");
+
+        stmt.unwrap().query_row([offscreen_id], |r| {
+            r.get(0)
+        }).ok()
+    }
+
+    /// external chunk offset of the block data for the offscreen with the given ID
+    pub fn get_offscreen_exta_offset(&self, offscreen_id: i64) -> Option<i64> {
+        let stmt = self.conn().prepare_cached(
+            "SELECT ExternalChunk.Offset FROM ExternalChunk \
+            INNER JOIN Offscreen ON hex(ExternalChunk.ExternalID) = hex(Offscreen.BlockData) \
+            WHERE Offscreen.MainId=?1"
+        );
+
+        stmt.unwrap().query_row([offscreen_id], |r| {
+            r.get(0)
+        }).ok()
+    }
+
+    // layer -> mipmap -> base mipmap info (100% scale) -> offscreen
+    fn get_base_offscreen_id(&self, mipmap_column: &str, layer_id: i64) -> Option<i64> {
+        let stmt = self.conn().prepare_cached(&format!(
+            "SELECT MipmapInfo.Offscreen FROM Layer \
+            INNER JOIN Mipmap ON Mipmap.MainId = Layer.{mipmap_column} \
+            INNER JOIN MipmapInfo ON MipmapInfo.MainId = Mipmap.BaseMipmapInfo \
+            WHERE Layer.MainId=?1"
+        ));
+
+        stmt.unwrap().query_row([layer_id], |r| {
+            r.get(0)
+        }).ok()
+    }
+
+    /// offscreen ID of the full size render of the given layer
+    pub fn get_layer_render_offscreen_id(&self, layer_id: i64) -> Option<i64> {
+        self.get_base_offscreen_id("LayerRenderMipmap", layer_id)
+    }
+
+    /// offscreen ID of the full size layer mask of the given layer if it has one
+    pub fn get_layer_mask_offscreen_id(&self, layer_id: i64) -> Option<i64> {
+        self.get_base_offscreen_id("LayerLayerMaskMipmap", layer_id)
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{CanvasUnit, ClipDb};
+use crate::chunks::color_channel;
+
+/// page settings new canvases in the project start with
+/// the columns come and go between CSP versions so every field is None when its column is missing
+#[derive(Debug, PartialEq, Clone)]
+pub struct DefaultPage {
+    pub unit: Option<CanvasUnit>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub resolution_dpi: Option<f64>,
+    pub channel_bytes: Option<i64>,
+    pub channel_order: Option<i64>,
+    pub color_type: Option<i64>,
+    pub tone_line: Option<f64>, // lines per inch for tones
+    pub use_paper: Option<bool>,
+    pub paper_color: Option<[u8; 3]>,
+    pub use_template: Option<bool>,
+    pub template_name: Option<String>,
+    pub template_path: Option<String>,
+    pub template_uuid: Option<String>,
+    pub is_user_template: Option<bool>,
+    pub double_page: Option<bool>,
+    pub preset_id: Option<String>,
+    pub use_crop_frame: Option<bool>,
+    pub setting_type: Option<String>, // "Illust", ...
+    pub record_time_lapse: Option<bool>,
+    // there's also black/white checked, book binding, cel templates and cover info
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Project {
+    pub internal_version: String, // "1.1.0" for every file so far
+    pub name: String, // seems to be a number
+    pub canvas_id: i64,
+    // only in files that have them
+    pub item_bank_id: Option<i64>,
+    pub cut_bank_id: Option<i64>,
+    pub root_canvas_node_id: Option<i64>,
+    pub default_page: DefaultPage,
+}
+
+impl<'a> ClipDb<'a> {
+    /// the project info, there's only ever one row
+    pub fn get_project(&self) -> Option<Project> {
+        let optional = |column: &str| { self.column_or_null("Project", column) };
+
+        let columns = [
+            "ProjectItemBank",
+            "ProjectCutBank",
+            "ProjectRootCanvasNode",
+            "DefaultPageUnit",
+            "DefaultPageWidth",
+            "DefaultPageHeight",
+            "DefaultPageResolution",
+            "DefaultPageChannelBytes",
+            "DefaultPageChannelOrder",
+            "DefaultPageColorType",
+            "DefaultPageToneLine",
+            "DefaultPageUsePaper",
+            "DefaultPagePaperRed",
+            "DefaultPagePaperGreen",
+            "DefaultPagePaperBlue",
+            "DefaultPageUseTemplate",
+            "DefaultPageTemplateName",
+            "DefaultPageTemplatePath",
+            "DefaultPageTemplateUUID",
+            "DefaultPageIsUserTemplate",
+            "DefaultPageDoublePage",
+            "DefaultPagePresetID",
+            "DefaultPageUseCropFrame",
+            "DefaultPageSettingType",
+            "DefaultPageRecordTimeLapse",
+        ].map(optional);
+
+        let stmt = self.conn().prepare_cached(&format!("SELECT \
+                ProjectInternalVersion, \
+                ProjectName, \
+                ProjectCanvas, \
+                {} \
+            FROM Project",
+            columns.join(", "),
+        ));
+
+        stmt.ok()?.query_row([], |r| {
+            let flag = |i: usize| -> rusqlite::Result<Option<bool>> { Ok(r.get::<_, Option<i64>>(i)?.map(|v| { v != 0 })) };
+            let channel = |i: usize| -> rusqlite::Result<Option<u8>> { Ok(r.get::<_, Option<i64>>(i)?.map(|v| { color_channel(v as u32) })) };
+            // empty strings are used for unset paths
+            let text = |i: usize| -> rusqlite::Result<Option<String>> { Ok(r.get::<_, Option<String>>(i)?.filter(|s| { !s.is_empty() })) };
+
+            let paper_color = match (channel(15)?, channel(16)?, channel(17)?) {
+                (Some(red), Some(green), Some(blue)) => Some([red, green, blue]),
+                _ => None,
+            };
+
+            let default_page = DefaultPage {
+                unit: r.get(6)?,
+                width: r.get(7)?,
+                height: r.get(8)?,
+                resolution_dpi: r.get(9)?,
+                channel_bytes: r.get(10)?,
+                channel_order: r.get(11)?,
+                color_type: r.get(12)?,
+                tone_line: r.get(13)?,
+                use_paper: flag(14)?,
+                paper_color,
+                use_template: flag(18)?,
+                template_name: text(19)?,
+                template_path: text(20)?,
+                template_uuid: text(21)?,
+                is_user_template: flag(22)?,
+                double_page: flag(23)?,
+                preset_id: text(24)?,
+                use_crop_frame: flag(25)?,
+                setting_type: text(26)?,
+                record_time_lapse: flag(27)?,
+            };
+
+            Ok(Project {
+                internal_version: r.get(0)?,
+                name: r.get(1)?,
+                canvas_id: r.get(2)?,
+                item_bank_id: r.get(3)?,
+                cut_bank_id: r.get(4)?,
+                root_canvas_node_id: r.get(5)?,
+                default_page,
+            })
+        }).ok()
+    }
+}
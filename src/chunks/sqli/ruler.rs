@@ -0,0 +1,348 @@
+use nom::multi::count;
+use nom::number::complete::be_f64;
+
+use crate::{CanvasUnit, ClipDb, Row};
+use crate::chunks::sqli::walk_list;
+
+// rulers hang off a layer's SpecialRulerManager, each kind in its own linked list
+// positions are in canvas pixels
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct VanishingPoint {
+    pub id: i64,
+    pub flags: i64, // bit 0: at infinity (lines are parallel at parallel_angle)
+    pub point: (f64, f64),
+    pub parallel_angle: f64, // degrees
+    pub guides: Vec<(f64, f64)>, // a point on each guide line
+}
+
+impl VanishingPoint {
+    pub fn is_parallel(&self) -> bool {
+        self.flags & 1 != 0
+    }
+
+    /// each guide line as a point on it and its direction
+    /// (towards the guide point from the vanishing point, or along the parallel angle)
+    pub fn guide_lines(&self) -> Vec<((f64, f64), (f64, f64))> {
+        self.guides.iter().map(|&(x, y)| {
+            if self.is_parallel() {
+                let (s, c) = self.parallel_angle.to_radians().sin_cos();
+                ((x, y), (c, s))
+            } else {
+                (self.point, (x - self.point.0, y - self.point.1))
+            }
+        }).collect()
+    }
+
+    // Guide: GuideNumber (x, y) f64 BE pairs, GuideDataSize bytes each
+    fn parse_guides(data: &[u8], number: usize, size: usize) -> Vec<(f64, f64)> {
+        data.chunks(size.max(16)).take(number).filter_map(|g| {
+            let (_, v) = count(be_f64::<_, nom::error::Error<&[u8]>>, 2)(g).ok()?;
+            Some((v[0], v[1]))
+        }).collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PerspectiveRuler {
+    pub id: i64,
+    pub layer_id: i64,
+    pub flags: i64,
+    pub kind: i64, // PerspectiveType, number of vanishing points?
+    pub eye_level_handle: (f64, f64),
+    pub move_handle: (f64, f64),
+    pub grid_origin: (f64, f64),
+    pub grid_flags: i64,
+    pub grid_size: f64,
+    pub camera_near: f64,
+    pub vanishing_points: Vec<VanishingPoint>,
+}
+
+impl PerspectiveRuler {
+    /// the eye level (horizon) as a point and direction, it goes through the eye level handle
+    /// and is parallel to the line between the first two finite vanishing points (horizontal if there aren't two)
+    pub fn eye_level(&self) -> ((f64, f64), (f64, f64)) {
+        let finite: Vec<&VanishingPoint> = self.vanishing_points.iter().filter(|v| { !v.is_parallel() }).collect();
+
+        let dir = match finite.as_slice() {
+            [a, b, ..] if a.point != b.point => (b.point.0 - a.point.0, b.point.1 - a.point.1),
+            _ => (1.0, 0.0),
+        };
+
+        (self.eye_level_handle, dir)
+    }
+}
+
+/// what a special ruler is and its geometry
+#[derive(Debug, PartialEq, Clone)]
+pub enum SpecialRulerKind {
+    Parallel { center: (f64, f64), angle: f64 }, // parallel line ruler through center
+    CurveParallel { curve_kind: i64, points: Vec<(f64, f64)> },
+    MultiCurve { curve_kind: i64, center: (f64, f64), offset_angle: f64, points: Vec<(f64, f64)> },
+    Emit { center: (f64, f64) }, // radial lines
+    CurveEmit { curve_kind: i64, points: Vec<(f64, f64)> },
+    ConcentricCircle { center: (f64, f64), radius: (f64, f64), angle: f64 },
+    Guide { horizontal: bool, position: (f64, f64) },
+    Perspective(PerspectiveRuler),
+    Symmetry { center: (f64, f64), angle: f64, lines: i64, line_symmetry: bool },
+}
+
+/// a ruler from one of the linked lists of a SpecialRulerManager
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpecialRuler {
+    pub id: i64,
+    pub layer_id: i64,
+    pub snap: bool,
+    pub kind: SpecialRulerKind,
+}
+
+impl SpecialRulerKind {
+    /// the SpecialRulerManager column with the first ruler and the table of each kind
+    pub const TABLES: [(&'static str, &'static str); 9] = [
+        ("FirstParallel", "RulerParallel"),
+        ("FirstCurveParallel", "RulerCurveParallel"),
+        ("FirstMultiCurve", "RulerMultiCurve"),
+        ("FirstEmit", "RulerEmit"),
+        ("FirstCurveEmit", "RulerCurveEmit"),
+        ("FirstConcentricCircle", "RulerConcentricCircle"),
+        ("FirstGuide", "RulerGuide"),
+        ("FirstPerspective", "RulerPerspective"),
+        ("FirstSymmetry", "RulerSymmetry"),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpecialRulerKind::Parallel { .. } => "parallel",
+            SpecialRulerKind::CurveParallel { .. } => "curve_parallel",
+            SpecialRulerKind::MultiCurve { .. } => "multi_curve",
+            SpecialRulerKind::Emit { .. } => "emit",
+            SpecialRulerKind::CurveEmit { .. } => "curve_emit",
+            SpecialRulerKind::ConcentricCircle { .. } => "concentric_circle",
+            SpecialRulerKind::Guide { .. } => "guide",
+            SpecialRulerKind::Perspective(_) => "perspective",
+            SpecialRulerKind::Symmetry { .. } => "symmetry",
+        }
+    }
+}
+
+/// a guide line across the whole canvas
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Guide {
+    pub id: i64,
+    pub layer_id: i64,
+    pub horizontal: bool,
+    pub position: f64, // canvas pixels, y for horizontal guides and x for vertical ones
+    pub snap: bool,
+    pub guide_move: i64, // ?: Layer.GuideMove of the owning layer, 0 in the samples
+}
+
+impl Guide {
+    /// the position in another unit (at the canvas resolution for physical units)
+    pub fn position_in(&self, unit: CanvasUnit, dpi: f64) -> Option<f64> {
+        CanvasUnit::Pixels.convert(self.position, unit, dpi)
+    }
+
+    /// the guide as a point on it and its direction
+    pub fn line(&self) -> ((f64, f64), (f64, f64)) {
+        if self.horizontal { ((0.0, self.position), (1.0, 0.0)) } else { ((self.position, 0.0), (0.0, 1.0)) }
+    }
+}
+
+// PointData: there are no samples, assumed to be f64 BE (x, y) pairs with an optional u32 count first
+fn parse_point_data(data: &[u8]) -> Vec<(f64, f64)> {
+    let data = match data.len() % 16 {
+        4 => &data[4..],
+        8 => &data[8..],
+        _ => data,
+    };
+
+    data.chunks_exact(16).map(|c| {
+        let v = |b: &[u8]| { f64::from_be_bytes(b.try_into().unwrap()) };
+        (v(&c[..8]), v(&c[8..]))
+    }).collect()
+}
+
+pub(crate) fn row_f64(row: &Row, column: &str) -> f64 {
+    row.get(column).and_then(|v| { v.as_f64() }).unwrap_or(0.0)
+}
+
+pub(crate) fn row_i64(row: &Row, column: &str) -> i64 {
+    row.get(column).and_then(|v| { v.as_i64() }).unwrap_or(0)
+}
+
+fn row_point(row: &Row, x: &str, y: &str) -> (f64, f64) {
+    (row_f64(row, x), row_f64(row, y))
+}
+
+fn row_points(row: &Row) -> Vec<(f64, f64)> {
+    row.get("PointData").and_then(|v| { v.as_bytes() }).map(parse_point_data).unwrap_or_default()
+}
+
+impl<'a> ClipDb<'a> {
+    /// the first item of the given kind of ruler (`FirstPerspective`, ...) of a ruler manager
+    pub(crate) fn get_first_ruler_id(&self, manager_id: i64, column: &str) -> Option<i64> {
+        if !self.column_exists("SpecialRulerManager", column) {
+            return None;
+        }
+
+        let stmt = self.conn().prepare_cached(&format!("SELECT {column} FROM SpecialRulerManager WHERE MainId=?1"));
+        stmt.ok()?.query_row([manager_id], |r| { r.get::<_, Option<i64>>(0) }).ok().flatten().filter(|id| { *id != 0 })
+    }
+
+    fn get_vanishing_points(&self, first_id: i64) -> Vec<VanishingPoint> {
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                MainId, Flag, VanishPointX, VanishPointY, ParallelAngle, GuideNumber, GuideDataSize, Guide, NextIndex \
+            FROM RulerVanishPoint WHERE MainId=?1") else { return vec![] };
+
+        walk_list(first_id, |id| {
+            let (point, next_id) = stmt.query_row([id], |r| {
+                let f = |i: usize| -> rusqlite::Result<f64> { Ok(r.get::<_, Option<f64>>(i)?.unwrap_or(0.0)) };
+                let number = r.get::<_, Option<i64>>(5)?.unwrap_or(0).max(0) as usize;
+                let size = r.get::<_, Option<i64>>(6)?.unwrap_or(16).max(0) as usize;
+                let guide: Option<Vec<u8>> = r.get(7)?;
+
+                let point = VanishingPoint {
+                    id: r.get(0)?,
+                    flags: r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                    point: (f(2)?, f(3)?),
+                    parallel_angle: f(4)?,
+                    guides: guide.map(|g| { VanishingPoint::parse_guides(&g, number, size) }).unwrap_or_default(),
+                };
+                Ok((point, r.get::<_, Option<i64>>(8)?.unwrap_or(0)))
+            }).ok()?;
+
+            Some((point, next_id))
+        })
+    }
+
+    /// perspective rulers of the ruler manager (`Layer.special_ruler_manager_id`) in order
+    pub fn get_perspective_rulers(&self, manager_id: i64) -> Vec<PerspectiveRuler> {
+        let Some(first) = self.get_first_ruler_id(manager_id, "FirstPerspective") else { return vec![] };
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                MainId, LayerId, Flag, PerspectiveType, EyeLevelHandleX, EyeLevelHandleY, MoveHandleX, MoveHandleY, \
+                GridOriginX, GridOriginY, GridFlag, GridSize, CameraNear, FirstVanishIndex, NextIndex \
+            FROM RulerPerspective WHERE MainId=?1") else { return vec![] };
+
+        walk_list(first, |id| {
+            let (mut ruler, (first_vanish, next_id)) = stmt.query_row([id], |r| {
+                let f = |i: usize| -> rusqlite::Result<f64> { Ok(r.get::<_, Option<f64>>(i)?.unwrap_or(0.0)) };
+                let n = |i: usize| -> rusqlite::Result<i64> { Ok(r.get::<_, Option<i64>>(i)?.unwrap_or(0)) };
+
+                let ruler = PerspectiveRuler {
+                    id: r.get(0)?,
+                    layer_id: n(1)?,
+                    flags: n(2)?,
+                    kind: n(3)?,
+                    eye_level_handle: (f(4)?, f(5)?),
+                    move_handle: (f(6)?, f(7)?),
+                    grid_origin: (f(8)?, f(9)?),
+                    grid_flags: n(10)?,
+                    grid_size: f(11)?,
+                    camera_near: f(12)?,
+                    vanishing_points: vec![],
+                };
+                Ok((ruler, (n(13)?, n(14)?)))
+            }).ok()?;
+            ruler.vanishing_points = self.get_vanishing_points(first_vanish);
+
+            Some((ruler, next_id))
+        })
+    }
+
+    /// the `Layer.GuideMove` value of the given layer
+    fn get_layer_guide_move(&self, layer_id: i64) -> Option<i64> {
+        if !self.column_exists("Layer", "GuideMove") {
+            return None;
+        }
+
+        let stmt = self.conn().prepare_cached("SELECT GuideMove FROM Layer WHERE MainId=?1");
+        stmt.ok()?.query_row([layer_id], |r| { r.get::<_, Option<i64>>(0) }).ok().flatten()
+    }
+
+    /// the guides of the ruler manager (`Layer.special_ruler_manager_id`) in order
+    pub fn get_guides(&self, manager_id: i64) -> Vec<Guide> {
+        self.get_ruler_list(manager_id, "FirstGuide", "RulerGuide").into_iter().filter_map(|r| {
+            let SpecialRulerKind::Guide { horizontal, position } = r.kind else { return None };
+
+            Some(Guide {
+                id: r.id,
+                layer_id: r.layer_id,
+                horizontal,
+                position: if horizontal { position.1 } else { position.0 },
+                snap: r.snap,
+                guide_move: self.get_layer_guide_move(r.layer_id).unwrap_or(0),
+            })
+        }).collect()
+    }
+
+    fn special_ruler_kind(&self, table: &str, row: &Row) -> Option<SpecialRulerKind> {
+        let center = || { row_point(row, "CenterX", "CenterY") };
+
+        let kind = match table {
+            "RulerParallel" => SpecialRulerKind::Parallel { center: center(), angle: row_f64(row, "Rotate") },
+            "RulerCurveParallel" => SpecialRulerKind::CurveParallel { curve_kind: row_i64(row, "CurveKind"), points: row_points(row) },
+            "RulerMultiCurve" => SpecialRulerKind::MultiCurve {
+                curve_kind: row_i64(row, "CurveKind"),
+                center: center(),
+                offset_angle: row_f64(row, "OffsetAngle"),
+                points: row_points(row),
+            },
+            "RulerEmit" => SpecialRulerKind::Emit { center: center() },
+            "RulerCurveEmit" => SpecialRulerKind::CurveEmit { curve_kind: row_i64(row, "CurveKind"), points: row_points(row) },
+            "RulerConcentricCircle" => SpecialRulerKind::ConcentricCircle {
+                center: center(),
+                radius: row_point(row, "RadiusX", "RadiusY"),
+                angle: row_f64(row, "Rotate"),
+            },
+            "RulerGuide" => SpecialRulerKind::Guide { horizontal: row_i64(row, "IsHorz") != 0, position: center() },
+            "RulerSymmetry" => SpecialRulerKind::Symmetry {
+                center: center(),
+                angle: row_f64(row, "Rotate"),
+                lines: row_i64(row, "LineNumber"),
+                line_symmetry: row_i64(row, "LineSymmetry") != 0,
+            },
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    // one of the manager's lists, except perspective rulers
+    fn get_ruler_list(&self, manager_id: i64, column: &str, table: &str) -> Vec<SpecialRuler> {
+        let Some(first) = self.get_first_ruler_id(manager_id, column) else { return vec![] };
+
+        let rows = walk_list(first, |id| {
+            let row = self.row(table, id)?;
+            let next_id = row_i64(&row, "NextIndex");
+            Some((row, next_id))
+        });
+
+        rows.into_iter().filter_map(|row| {
+            Some(SpecialRuler {
+                id: row.main_id().unwrap_or(0),
+                layer_id: row_i64(&row, "LayerId"),
+                snap: row_i64(&row, "Snap") != 0,
+                kind: self.special_ruler_kind(table, &row)?,
+            })
+        }).collect()
+    }
+
+    /// every ruler of the ruler manager (`Layer.special_ruler_manager_id`), grouped by kind in the manager's column order
+    pub fn get_special_rulers(&self, manager_id: i64) -> Vec<SpecialRuler> {
+        let mut rulers = vec![];
+
+        for (column, table) in SpecialRulerKind::TABLES {
+            if table == "RulerPerspective" {
+                rulers.extend(self.get_perspective_rulers(manager_id).into_iter().map(|p| {
+                    // no Snap column, guessed to be bit 0 of the flags like the vanishing points
+                    SpecialRuler { id: p.id, layer_id: p.layer_id, snap: p.flags & 1 != 0, kind: SpecialRulerKind::Perspective(p) }
+                }));
+                continue;
+            }
+
+            rulers.extend(self.get_ruler_list(manager_id, column, table));
+        }
+
+        rulers
+    }
+}
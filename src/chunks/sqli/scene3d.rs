@@ -0,0 +1,476 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ClipDb;
+
+// 3d layers have a Manager3DOd with the (encoded) scene and a list of LayerObjects
+// positions are in CSP's 3d units (cm?)
+
+/// the 3d scene of a 3d layer
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scene3D {
+    pub id: i64,
+    pub layer_id: i64,
+    pub canvas_rect: [f64; 4], // left, top, right, bottom of the canvas in the 3d view
+    pub near_far_auto: bool,
+    pub scene_exta_offset: Option<i64>, // SceneData
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LayerObjectKind {
+    Camera(i64), // CameraInfo id
+    Light(i64), // LightInfo id
+    Character(i64),
+    Room(i64),
+    SmallObject(i64),
+    Folder(i64),
+    Unknown,
+}
+
+/// an object in a 3d layer's scene
+#[derive(Debug, PartialEq, Clone)]
+pub struct LayerObject {
+    pub id: i64,
+    pub layer_id: i64,
+    pub uuid: String,
+    pub bank_item_uuid: Option<String>, // the material/model it was loaded from
+    pub name: String,
+    pub kind: LayerObjectKind,
+    pub locked: bool,
+    pub visible: bool,
+    pub selected: bool,
+    pub next_id: i64,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Frustum {
+    // at the near plane
+    pub left: f64,
+    pub right: f64,
+    pub top: f64,
+    pub bottom: f64,
+    pub near: f64,
+    pub far: f64,
+    pub ortho: bool,
+}
+
+impl Frustum {
+    /// opengl style projection matrix (to clip space with z in -1..1), column major
+    pub fn projection_matrix(&self) -> [f64; 16] {
+        let Frustum { left: l, right: r, top: t, bottom: b, near: n, far: f, .. } = *self;
+
+        if self.ortho {
+            return [
+                2.0 / (r - l), 0.0, 0.0, 0.0,
+                0.0, 2.0 / (t - b), 0.0, 0.0,
+                0.0, 0.0, -2.0 / (f - n), 0.0,
+                -(r + l) / (r - l), -(t + b) / (t - b), -(f + n) / (f - n), 1.0,
+            ];
+        }
+
+        [
+            2.0 * n / (r - l), 0.0, 0.0, 0.0,
+            0.0, 2.0 * n / (t - b), 0.0, 0.0,
+            (r + l) / (r - l), (t + b) / (t - b), -(f + n) / (f - n), -1.0,
+            0.0, 0.0, -2.0 * f * n / (f - n), 0.0,
+        ]
+    }
+
+    /// vertical field of view in radians
+    pub fn fov_y(&self) -> f64 {
+        2.0 * ((self.top - self.bottom) / 2.0 / self.near).atan()
+    }
+
+    pub fn aspect(&self) -> f64 {
+        (self.right - self.left) / (self.top - self.bottom)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Camera {
+    pub id: i64,
+    pub layer_id: i64,
+    pub layer_object_id: i64,
+    pub position: [f64; 3],
+    pub target: [f64; 3],
+    pub up: [f64; 3],
+    pub twist: f64, // degrees around the view direction?
+    pub zoom_with_dolly: bool,
+    pub frustum: Frustum,
+    pub viewport: [i64; 4], // x, y, width, height in canvas pixels
+    pub optical_axis: [f64; 2], // where the view direction hits the canvas
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LightKind {
+    Ambient, // 0
+    Parallel, // 1
+    Unknown(i64),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Light {
+    pub id: i64,
+    pub layer_id: i64,
+    pub layer_object_id: i64,
+    pub index: i64,
+    pub uuid: String,
+    pub kind: LightKind,
+}
+
+/// everything in the database about a 3d layer
+#[derive(Debug, PartialEq, Clone)]
+pub struct Layer3D {
+    pub scene: Scene3D,
+    pub objects: Vec<LayerObject>,
+    pub cameras: Vec<Camera>,
+    pub lights: Vec<Light>,
+}
+
+impl Layer3D {
+    /// the camera the layer is rendered with (the first one)
+    pub fn camera(&self) -> Option<&Camera> {
+        self.cameras.first()
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len <= f64::EPSILON { v } else { v.map(|x| { x / len }) }
+}
+
+impl Camera {
+    /// right, up and backward (looking down -z) unit axes of the camera, twist included
+    pub fn axes(&self) -> [[f64; 3]; 3] {
+        let z = normalize(sub(self.position, self.target));
+        let x = normalize(cross(self.up, z));
+        let y = cross(z, x);
+
+        let (s, c) = self.twist.to_radians().sin_cos();
+        let rotate = |a: [f64; 3], b: [f64; 3]| -> [f64; 3] { [0, 1, 2].map(|i| { a[i] * c + b[i] * s }) };
+
+        [rotate(x, y), rotate(y, x.map(|v| { -v })), z]
+    }
+
+    /// camera to world transform, column major
+    pub fn world_matrix(&self) -> [f64; 16] {
+        let [x, y, z] = self.axes();
+        let p = self.position;
+
+        [
+            x[0], x[1], x[2], 0.0,
+            y[0], y[1], y[2], 0.0,
+            z[0], z[1], z[2], 0.0,
+            p[0], p[1], p[2], 1.0,
+        ]
+    }
+
+    /// world to camera transform (the inverse of `world_matrix`), column major
+    pub fn view_matrix(&self) -> [f64; 16] {
+        let [x, y, z] = self.axes();
+        let p = self.position;
+        let dot = |a: [f64; 3]| { a[0] * p[0] + a[1] * p[1] + a[2] * p[2] };
+
+        [
+            x[0], y[0], z[0], 0.0,
+            x[1], y[1], z[1], 0.0,
+            x[2], y[2], z[2], 0.0,
+            -dot(x), -dot(y), -dot(z), 1.0,
+        ]
+    }
+
+    pub fn projection_matrix(&self) -> [f64; 16] {
+        self.frustum.projection_matrix()
+    }
+
+    /// projection * view, column major
+    pub fn view_projection_matrix(&self) -> [f64; 16] {
+        mul(&self.projection_matrix(), &self.view_matrix())
+    }
+
+    /// where a point in the scene ends up on the canvas (in pixels, y down), None if it's behind the camera
+    pub fn project(&self, point: [f64; 3]) -> Option<[f64; 2]> {
+        let m = self.view_projection_matrix();
+        let clip = [0, 1, 3].map(|r| { m[r] * point[0] + m[4 + r] * point[1] + m[8 + r] * point[2] + m[12 + r] });
+
+        if clip[2] <= f64::EPSILON {
+            return None;
+        }
+
+        let (ndc_x, ndc_y) = (clip[0] / clip[2], clip[1] / clip[2]);
+        let [vx, vy, vw, vh] = self.viewport.map(|v| { v as f64 });
+
+        Some([vx + (ndc_x + 1.0) / 2.0 * vw, vy + (1.0 - ndc_y) / 2.0 * vh])
+    }
+}
+
+// column major 4x4 product
+fn mul(a: &[f64; 16], b: &[f64; 16]) -> [f64; 16] {
+    let mut out = [0.0; 16];
+
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| { a[k * 4 + row] * b[col * 4 + k] }).sum();
+        }
+    }
+
+    out
+}
+
+impl<'a> ClipDb<'a> {
+    pub fn get_scene_3d(&self, layer_id: i64) -> Option<Scene3D> {
+        let stmt = self.conn().prepare_cached("SELECT \
+                Manager3DOd.MainId, LayerId, CanvasRectLeft, CanvasRectTop, CanvasRectRight, CanvasRectBottom, CameraNearFarAutoSet, ExternalChunk.Offset \
+            FROM Manager3DOd \
+            LEFT JOIN ExternalChunk ON hex(ExternalChunk.ExternalID) = hex(Manager3DOd.SceneData) \
+            WHERE LayerId=?1");
+
+        stmt.ok()?.query_row([layer_id], |r| {
+            let f = |i: usize| -> rusqlite::Result<f64> { Ok(r.get::<_, Option<f64>>(i)?.unwrap_or(0.0)) };
+
+            Ok(Scene3D {
+                id: r.get(0)?,
+                layer_id: r.get(1)?,
+                canvas_rect: [f(2)?, f(3)?, f(4)?, f(5)?],
+                near_far_auto: r.get::<_, Option<i64>>(6)?.unwrap_or(0) != 0,
+                scene_exta_offset: r.get(7)?,
+            })
+        }).ok()
+    }
+
+    /// objects of a 3d layer in scene order (following ObjectNext)
+    pub fn get_layer_objects(&self, layer_id: i64) -> Vec<LayerObject> {
+        let Ok(mut stmt) = self.conn().prepare_cached(&format!("SELECT \
+                MainId, LayerId, ObjectUuid, {}, ObjectName, ObjectLock, ObjectVisibility, ObjectSelect, ObjectNext, \
+                {}, {}, {}, {}, {}, {} \
+            FROM LayerObject WHERE LayerId=?1",
+            self.column_or_null("LayerObject", "BankItemUuid"),
+            self.column_or_null("LayerObject", "Camera"),
+            self.column_or_null("LayerObject", "Light"),
+            self.column_or_null("LayerObject", "Character"),
+            self.column_or_null("LayerObject", "Room"),
+            self.column_or_null("LayerObject", "SmallObject"),
+            self.column_or_null("LayerObject", "Folder"),
+        )) else { return vec![] };
+
+        let objects = stmt.query_map([layer_id], |r| {
+            let link = |i: usize| -> rusqlite::Result<Option<i64>> { Ok(r.get::<_, Option<i64>>(i)?.filter(|id| { *id != 0 })) };
+            let flag = |i: usize| -> rusqlite::Result<bool> { Ok(r.get::<_, Option<i64>>(i)?.unwrap_or(0) != 0) };
+
+            let kind = [
+                link(9)?.map(LayerObjectKind::Camera),
+                link(10)?.map(LayerObjectKind::Light),
+                link(11)?.map(LayerObjectKind::Character),
+                link(12)?.map(LayerObjectKind::Room),
+                link(13)?.map(LayerObjectKind::SmallObject),
+                link(14)?.map(LayerObjectKind::Folder),
+            ].into_iter().flatten().next().unwrap_or(LayerObjectKind::Unknown);
+
+            Ok(LayerObject {
+                id: r.get(0)?,
+                layer_id: r.get(1)?,
+                uuid: r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                bank_item_uuid: r.get::<_, Option<String>>(3)?.filter(|u| { !u.is_empty() }),
+                name: r.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                kind,
+                locked: flag(5)?,
+                visible: flag(6)?,
+                selected: flag(7)?,
+                next_id: r.get::<_, Option<i64>>(8)?.unwrap_or(0),
+            })
+        });
+
+        let mut by_id: HashMap<i64, LayerObject> = objects.map(|rows| { rows.filter_map(|r| { r.ok() }).map(|o| { (o.id, o) }).collect() }).unwrap_or_default();
+
+        // the first object is the one nothing points to
+        let linked: HashSet<i64> = by_id.values().map(|o| { o.next_id }).collect();
+        let mut heads: Vec<i64> = by_id.keys().copied().filter(|id| { !linked.contains(id) }).collect();
+        heads.sort();
+
+        let mut out = vec![];
+        for head in heads {
+            let mut next = head;
+            while let Some(object) = by_id.remove(&next) {
+                next = object.next_id;
+                out.push(object);
+            }
+        }
+
+        // anything left is in a cycle
+        let mut rest: Vec<LayerObject> = by_id.into_values().collect();
+        rest.sort_by_key(|o| { o.id });
+        out.extend(rest);
+
+        out
+    }
+
+    pub fn get_camera(&self, camera_id: i64) -> Option<Camera> {
+        let stmt = self.conn().prepare_cached("SELECT \
+                MainId, LayerId, LayerObjectId, \
+                CameraPositionX, CameraPositionY, CameraPositionZ, \
+                CameraTargetX, CameraTargetY, CameraTargetZ, \
+                CameraUpX, CameraUpY, CameraUpZ, \
+                CameraTwist, \
+                FrustumLeft, FrustumRight, FrustumTop, FrustumBottom, FrustumNear, FrustumFar, FrustumOrtho, \
+                CameraZoomWithDolly, ViewportXmin, ViewportYmin, ViewportWidth, ViewportHeight, \
+                LayerOpticalAxisPtX, LayerOpticalAxisPtY \
+            FROM CameraInfo WHERE MainId=?1");
+
+        stmt.ok()?.query_row([camera_id], |r| {
+            let f = |i: usize| -> rusqlite::Result<f64> { Ok(r.get::<_, Option<f64>>(i)?.unwrap_or(0.0)) };
+            let n = |i: usize| -> rusqlite::Result<i64> { Ok(r.get::<_, Option<i64>>(i)?.unwrap_or(0)) };
+
+            Ok(Camera {
+                id: r.get(0)?,
+                layer_id: r.get(1)?,
+                layer_object_id: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                position: [f(3)?, f(4)?, f(5)?],
+                target: [f(6)?, f(7)?, f(8)?],
+                up: [f(9)?, f(10)?, f(11)?],
+                twist: f(12)?,
+                zoom_with_dolly: n(20)? != 0,
+                frustum: Frustum {
+                    left: f(13)?,
+                    right: f(14)?,
+                    top: f(15)?,
+                    bottom: f(16)?,
+                    near: f(17)?,
+                    far: f(18)?,
+                    ortho: n(19)? != 0,
+                },
+                viewport: [n(21)?, n(22)?, n(23)?, n(24)?],
+                optical_axis: [f(25)?, f(26)?],
+            })
+        }).ok()
+    }
+
+    pub fn get_light(&self, light_id: i64) -> Option<Light> {
+        let stmt = self.conn().prepare_cached("SELECT \
+                MainId, LayerId, LayerObjectId, LightIndex, LightUuid, LightType \
+            FROM LightInfo WHERE MainId=?1");
+
+        stmt.ok()?.query_row([light_id], |r| {
+            Ok(Light {
+                id: r.get(0)?,
+                layer_id: r.get(1)?,
+                layer_object_id: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                index: r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                uuid: r.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                kind: match r.get::<_, Option<i64>>(5)?.unwrap_or(0) {
+                    0 => LightKind::Ambient,
+                    1 => LightKind::Parallel,
+                    k => LightKind::Unknown(k),
+                },
+            })
+        }).ok()
+    }
+
+    /// the scene, objects, cameras and lights of a 3d layer, None for other layers
+    pub fn get_layer_3d(&self, layer_id: i64) -> Option<Layer3D> {
+        let scene = self.get_scene_3d(layer_id)?;
+        let objects = self.get_layer_objects(layer_id);
+
+        let cameras = objects.iter().filter_map(|o| {
+            match o.kind { LayerObjectKind::Camera(id) => self.get_camera(id), _ => None }
+        }).collect();
+
+        let lights = objects.iter().filter_map(|o| {
+            match o.kind { LayerObjectKind::Light(id) => self.get_light(id), _ => None }
+        }).collect();
+
+        Some(Layer3D { scene, objects, cameras, lights })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRUSTUM: Frustum = Frustum { left: -1.0, right: 1.0, top: 1.0, bottom: -1.0, near: 1.0, far: 10.0, ortho: false };
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    // clip space x, y, z, w of a camera space point
+    fn transform(m: &[f64; 16], p: [f64; 3]) -> [f64; 4] {
+        [0, 1, 2, 3].map(|r| { m[r] * p[0] + m[4 + r] * p[1] + m[8 + r] * p[2] + m[12 + r] })
+    }
+
+    fn camera(frustum: Frustum) -> Camera {
+        Camera {
+            id: 1,
+            layer_id: 1,
+            layer_object_id: 1,
+            position: [0.0, 0.0, 10.0],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            twist: 0.0,
+            zoom_with_dolly: false,
+            frustum,
+            viewport: [0, 0, 100, 100],
+            optical_axis: [50.0, 50.0],
+        }
+    }
+
+    #[test]
+    fn perspective_projection() {
+        let m = FRUSTUM.projection_matrix();
+        assert_eq!((m[0], m[5], m[11], m[15]), (1.0, 1.0, -1.0, 0.0));
+
+        // the near and far planes map to -1 and 1
+        let near = transform(&m, [0.0, 0.0, -1.0]);
+        let far = transform(&m, [0.0, 0.0, -10.0]);
+        assert!(close(near[2] / near[3], -1.0));
+        assert!(close(far[2] / far[3], 1.0));
+
+        // the corner of the near plane is the corner of clip space
+        let corner = transform(&m, [1.0, 1.0, -1.0]);
+        assert!(close(corner[0] / corner[3], 1.0) && close(corner[1] / corner[3], 1.0));
+
+        assert!(close(FRUSTUM.fov_y(), std::f64::consts::FRAC_PI_2));
+        assert!(close(FRUSTUM.aspect(), 1.0));
+    }
+
+    #[test]
+    fn orthographic_projection() {
+        let m = Frustum { left: 0.0, right: 4.0, top: 2.0, bottom: -2.0, ortho: true, ..FRUSTUM }.projection_matrix();
+
+        let p = transform(&m, [2.0, 0.0, -1.0]);
+        assert_eq!(p[3], 1.0);
+        assert!(close(p[0], 0.0) && close(p[1], 0.0) && close(p[2], -1.0));
+
+        let p = transform(&m, [4.0, 2.0, -10.0]);
+        assert!(close(p[0], 1.0) && close(p[1], 1.0) && close(p[2], 1.0));
+    }
+
+    #[test]
+    fn project_onto_the_canvas() {
+        let cam = camera(FRUSTUM);
+
+        assert_eq!(cam.project([0.0, 0.0, 0.0]), Some([50.0, 50.0]));
+
+        // one unit in front of the camera the near plane spans the viewport, y goes down on the canvas
+        let [x, y] = cam.project([0.5, 0.5, 9.0]).unwrap();
+        assert!(close(x, 75.0) && close(y, 25.0));
+
+        assert_eq!(cam.project([0.0, 0.0, 11.0]), None);
+    }
+
+    #[test]
+    fn view_is_the_inverse_of_world() {
+        let cam = Camera { position: [3.0, 4.0, 5.0], target: [0.0, 1.0, 0.0], twist: 30.0, ..camera(FRUSTUM) };
+        let m = mul(&cam.view_matrix(), &cam.world_matrix());
+
+        for (i, v) in m.iter().enumerate() {
+            assert!(close(*v, if i % 5 == 0 { 1.0 } else { 0.0 }), "{m:?}");
+        }
+    }
+}
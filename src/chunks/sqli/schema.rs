@@ -0,0 +1,107 @@
+use std::collections::BTreeSet;
+
+use crate::ClipDb;
+
+// CSP only adds tables and columns once a file uses the feature they belong to
+// so the schema says more about what's in the file than which version wrote it
+
+/// features a file has data for, detected from its schema
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone)]
+pub enum Capability {
+    LayerEffects,
+    FilterLayers,
+    Vectors,
+    BrushStyles,
+    FillStyles,
+    Models3D,
+    PerspectiveRulers,
+    SpecialRulers,
+    Animation,
+    TimeLapse,
+    LayerThumbnails,
+    ColorManagement,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 12] = [
+        Capability::LayerEffects,
+        Capability::FilterLayers,
+        Capability::Vectors,
+        Capability::BrushStyles,
+        Capability::FillStyles,
+        Capability::Models3D,
+        Capability::PerspectiveRulers,
+        Capability::SpecialRulers,
+        Capability::Animation,
+        Capability::TimeLapse,
+        Capability::LayerThumbnails,
+        Capability::ColorManagement,
+    ];
+
+    // (table, column) whose presence means the file has the feature
+    fn marker(&self) -> (&'static str, Option<&'static str>) {
+        match self {
+            Capability::LayerEffects => ("Layer", Some("LayerEffectInfo")),
+            Capability::FilterLayers => ("Layer", Some("FilterLayerInfo")),
+            Capability::Vectors => ("VectorObjectList", None),
+            Capability::BrushStyles => ("BrushStyleManager", None),
+            Capability::FillStyles => ("FillStyle", None),
+            Capability::Models3D => ("Manager3DOd", None),
+            Capability::PerspectiveRulers => ("RulerPerspective", None),
+            Capability::SpecialRulers => ("SpecialRulerManager", None),
+            Capability::Animation => ("TimeLine", None),
+            Capability::TimeLapse => ("TimeLapseBlob", None),
+            Capability::LayerThumbnails => ("LayerThumbnail", None),
+            Capability::ColorManagement => ("Canvas", Some("CanvasSrcProfile")),
+        }
+    }
+}
+
+/// what is known about the format of a file
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct SchemaInfo {
+    pub internal_version: Option<String>, // ProjectInternalVersion
+    pub capabilities: BTreeSet<Capability>,
+}
+
+impl SchemaInfo {
+    pub fn has(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+impl<'a> ClipDb<'a> {
+    /// names of all columns in the given table, empty if it doesn't exist
+    pub fn columns(&self, table: &str) -> Vec<String> {
+        self.schema.with_columns(self.conn, table, |c| { c.to_vec() })
+    }
+
+    /// the column name if it exists or `NULL` so queries still work on files without it
+    pub(crate) fn column_or_null(&self, table: &str, column: &str) -> String {
+        if self.column_exists(table, column) { column.to_string() } else { "NULL".to_string() }
+    }
+
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        match capability.marker() {
+            (table, Some(column)) => self.column_exists(table, column),
+            (table, None) => self.table_exists(table),
+        }
+    }
+
+    /// detects the format version and features of the file
+    pub fn schema_info(&self) -> SchemaInfo {
+        let stmt = self.conn().prepare_cached(&format!(
+            "SELECT {} FROM Project",
+            self.column_or_null("Project", "ProjectInternalVersion")
+        ));
+
+        let internal_version = stmt.ok().and_then(|mut s| {
+            s.query_row([], |r| { r.get::<_, Option<String>>(0) }).ok().flatten()
+        });
+
+        SchemaInfo {
+            internal_version,
+            capabilities: Capability::ALL.into_iter().filter(|c| { self.has_capability(*c) }).collect(),
+        }
+    }
+}
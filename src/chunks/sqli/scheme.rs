@@ -0,0 +1,247 @@
+use num_enum::FromPrimitive;
+use rusqlite::Connection;
+use rusqlite::types::ValueRef;
+
+use crate::ClipDb;
+
+// every file describes its own tables in ParamScheme (columns) and ElemScheme (tables)
+// which makes it possible to read tables there's no model for yet
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, FromPrimitive)]
+#[repr(i64)]
+pub enum DataType {
+    Integer = 1,
+    Real = 2,
+    Text = 3,
+    Binary = 4,
+    #[num_enum(catch_all)]
+    Unknown(i64),
+}
+
+/// description of a column
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ParamScheme {
+    pub table: String,
+    pub label: String,
+    pub data_type: DataType,
+    pub flag: i64, // 4: MainId in another table, 6: MainId in the same table (next/child indices), Project and BrushStyle links are 0
+    pub owner_type: i64, // 2: canvas, 4: layer
+    pub link_table: Option<String>,
+}
+
+impl ParamScheme {
+    /// table the value of this column refers to, if any
+    /// LinkTable is always set for links except for some same table ones (flag 6)
+    pub fn link(&self) -> Option<&str> {
+        match self.link_table.as_deref().filter(|t| { !t.is_empty() }) {
+            Some(table) => Some(table),
+            None if self.flag == 6 => Some(&self.table),
+            None => None,
+        }
+    }
+}
+
+/// description of a table
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ElemScheme {
+    pub table: String,
+    pub elem_type: i64, // 0 for most, 4 for Layer, 2 for Canvas...
+    pub max_index: i64, // highest MainId used
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Value {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Real(v) => Some(*v),
+            Value::Integer(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Binary(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // stored type wins unless the scheme says it's text (some text is stored as blobs)
+    fn from_sql(value: ValueRef<'_>, data_type: Option<DataType>) -> Self {
+        match (value, data_type) {
+            (ValueRef::Null, _) => Value::Null,
+            (ValueRef::Blob(b), Some(DataType::Text)) => Value::Text(String::from_utf8_lossy(b).into_owned()),
+            (ValueRef::Integer(v), _) => Value::Integer(v),
+            (ValueRef::Real(v), _) => Value::Real(v),
+            (ValueRef::Text(t), _) => Value::Text(String::from_utf8_lossy(t).into_owned()),
+            (ValueRef::Blob(b), _) => Value::Binary(b.to_vec()),
+        }
+    }
+}
+
+/// a row of any table, columns are in table order
+#[derive(Debug, PartialEq, Clone)]
+pub struct Row {
+    pub table: String,
+    pub columns: Vec<(String, Value)>,
+}
+
+impl Row {
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        self.columns.iter().find(|(c, _)| { c == column }).map(|(_, v)| { v })
+    }
+
+    pub fn main_id(&self) -> Option<i64> {
+        self.get("MainId")?.as_i64()
+    }
+}
+
+// column descriptions of a table straight from the db, ClipDb::param_schemes caches them
+pub(crate) fn load_param_schemes(conn: &Connection, table: &str) -> Vec<ParamScheme> {
+    let stmt = conn.prepare_cached("SELECT \
+            TableName, \
+            LabelName, \
+            DataType, \
+            Flag, \
+            OwnerType, \
+            LinkTable \
+        FROM ParamScheme WHERE TableName=?1");
+
+    let Ok(mut stmt) = stmt else { return vec![] };
+
+    stmt.query_map([table], |r| {
+        Ok(ParamScheme {
+            table: r.get(0)?,
+            label: r.get(1)?,
+            data_type: DataType::from(r.get::<_, i64>(2)?),
+            flag: r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+            owner_type: r.get::<_, Option<i64>>(4)?.unwrap_or(0),
+            link_table: r.get(5)?,
+        })
+    }).map_or(vec![], |rows| { rows.filter_map(|r| { r.ok() }).collect() })
+}
+
+impl<'a> ClipDb<'a> {
+    /// column descriptions for the given table
+    pub fn param_schemes(&self, table: &str) -> Vec<ParamScheme> {
+        self.with_param_schemes(table, |s| { s.to_vec() })
+    }
+
+    /// descriptions of all tables the file knows about (they don't all exist)
+    pub fn elem_schemes(&self) -> Vec<ElemScheme> {
+        let stmt = self.conn().prepare_cached("SELECT TableName, ElemType, MaxIndex FROM ElemScheme");
+
+        let Ok(mut stmt) = stmt else { return vec![] };
+
+        stmt.query_map([], |r| {
+            Ok(ElemScheme {
+                table: r.get(0)?,
+                elem_type: r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                max_index: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+            })
+        }).map_or(vec![], |rows| { rows.filter_map(|r| { r.ok() }).collect() })
+    }
+
+    fn query_rows(&self, table: &str, main_id: Option<i64>) -> Vec<Row> {
+        // the name goes into the query so it has to be a real table
+        if !self.table_exists(table) {
+            return vec![];
+        }
+
+        let sql = match main_id {
+            Some(_) => format!("SELECT * FROM \"{table}\" WHERE MainId=?1"),
+            None => format!("SELECT * FROM \"{table}\""),
+        };
+
+        let Ok(mut stmt) = self.conn().prepare_cached(&sql) else { return vec![] };
+        let params: Vec<i64> = main_id.into_iter().collect();
+
+        // the data type of each column, in the statement's column order
+        let (names, types): (Vec<String>, Vec<Option<DataType>>) = self.with_param_schemes(table, |schemes| {
+            stmt.column_names().into_iter().map(|name| {
+                (name.to_string(), schemes.iter().find(|s| { s.label == name }).map(|s| { s.data_type }))
+            }).unzip()
+        });
+
+        stmt.query_map(rusqlite::params_from_iter(params), |r| {
+            let columns = names.iter().zip(&types).enumerate().map(|(i, (name, data_type))| {
+                (name.clone(), Value::from_sql(r.get_ref(i).unwrap_or(ValueRef::Null), *data_type))
+            }).collect();
+
+            Ok(Row { table: table.to_string(), columns })
+        }).map_or(vec![], |rows| { rows.filter_map(|r| { r.ok() }).collect() })
+    }
+
+    /// every row of the given table, empty if it doesn't exist
+    pub fn rows(&self, table: &str) -> impl Iterator<Item = Row> {
+        self.query_rows(table, None).into_iter()
+    }
+
+    /// the row of the given table with the given MainId
+    pub fn row(&self, table: &str, main_id: i64) -> Option<Row> {
+        self.query_rows(table, Some(main_id)).into_iter().next()
+    }
+
+    /// the row a column of `row` refers to according to its ParamScheme, None for 0 (no link) or non link columns
+    pub fn follow_link(&self, row: &Row, column: &str) -> Option<Row> {
+        let link = self.with_param_schemes(&row.table, |schemes| {
+            schemes.iter().find(|s| { s.label == column })?.link().map(String::from)
+        })?;
+        let id = row.get(column)?.as_i64().filter(|id| { *id != 0 })?;
+
+        self.row(&link, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheme(flag: i64, link_table: Option<&str>) -> ParamScheme {
+        ParamScheme {
+            table: "Layer".to_string(),
+            label: "Column".to_string(),
+            data_type: DataType::Integer,
+            flag,
+            owner_type: 4,
+            link_table: link_table.map(String::from),
+        }
+    }
+
+    #[test]
+    fn link_table_wins_over_flag() {
+        assert_eq!(scheme(4, Some("Offscreen")).link(), Some("Offscreen"));
+        assert_eq!(scheme(0, Some("Canvas")).link(), Some("Canvas"));
+        assert_eq!(scheme(6, Some("TimeLine")).link(), Some("TimeLine"));
+    }
+
+    #[test]
+    fn same_table_and_no_link() {
+        assert_eq!(scheme(6, None).link(), Some("Layer"));
+        assert_eq!(scheme(6, Some("")).link(), Some("Layer"));
+        assert_eq!(scheme(2, Some("")).link(), None);
+        assert_eq!(scheme(0, None).link(), None);
+    }
+}
@@ -0,0 +1,149 @@
+use num_enum::IntoPrimitive;
+
+use crate::ClipDb;
+use crate::chunks::color_channel;
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, IntoPrimitive)]
+#[repr(usize)]
+pub enum ThumbnailSize {
+    Smaller,
+    Small,
+    Middle,
+    Large,
+    Larger,
+    Middle2x,
+    Larger2x,
+}
+
+impl ThumbnailSize {
+    /// in the order of their columns, which is also their order in the offscreen
+    pub const ALL: [ThumbnailSize; 7] = [
+        ThumbnailSize::Smaller,
+        ThumbnailSize::Small,
+        ThumbnailSize::Middle,
+        ThumbnailSize::Large,
+        ThumbnailSize::Larger,
+        ThumbnailSize::Middle2x,
+        ThumbnailSize::Larger2x,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ThumbnailSize::Smaller => "Smaller",
+            ThumbnailSize::Small => "Small",
+            ThumbnailSize::Middle => "Middle",
+            ThumbnailSize::Large => "Large",
+            ThumbnailSize::Larger => "Larger",
+            ThumbnailSize::Middle2x => "Middle2x",
+            ThumbnailSize::Larger2x => "Larger2x",
+        }
+    }
+
+    /// width and height in pixels
+    pub fn pixels(&self) -> u32 {
+        match self {
+            ThumbnailSize::Smaller => 16,
+            ThumbnailSize::Small => 24,
+            ThumbnailSize::Middle => 40,
+            ThumbnailSize::Large => 64,
+            ThumbnailSize::Larger => 96,
+            ThumbnailSize::Middle2x => 80,
+            ThumbnailSize::Larger2x => 192,
+        }
+    }
+
+    /// where the thumbnail is stored in the (512x512) thumbnail offscreen as x, y, width, height
+    /// the sizes are stacked down the left edge in column order, which fills the 512 rows exactly
+    /// and puts Large at (0, 80), the only one that has been up to date in a sample
+    pub fn region(&self) -> (u32, u32, u32, u32) {
+        let y = ThumbnailSize::ALL.iter().take_while(|s| { *s != self }).map(|s| { s.pixels() }).sum();
+        (0, y, self.pixels(), self.pixels())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct LayerThumbnail {
+    pub id: i64,
+    pub layer_id: i64,
+    // Thumbnail*NeedRefresh columns are some kind of counter, 0 when the thumbnail is current
+    pub need_refresh: [bool; 7], // indexed by ThumbnailSize, true when the column is missing
+    pub canvas_width: i64,
+    pub canvas_height: i64,
+    pub use_draw_color: bool,
+    pub main_color: [u8; 3],
+    pub sub_color: [u8; 3],
+    pub offscreen_id: i64,
+    // there's also a bunch of ColorType/PrewviewColorType columns
+}
+
+impl LayerThumbnail {
+    pub fn needs_refresh(&self, size: ThumbnailSize) -> bool {
+        self.need_refresh[usize::from(size)]
+    }
+}
+
+impl<'a> ClipDb<'a> {
+    /// the render thumbnail info of the given layer if it has one
+    pub fn get_layer_thumbnail(&self, layer_id: i64) -> Option<LayerThumbnail> {
+        if !self.table_exists("LayerThumbnail") {
+            return None;
+        }
+
+        let refresh_columns: Vec<String> = ThumbnailSize::ALL.iter().map(|s| {
+            self.column_or_null("LayerThumbnail", &format!("Thumbnail{}NeedRefresh", s.name()))
+        }).collect();
+
+        let stmt = self.conn().prepare_cached(&format!("SELECT \
+                LayerThumbnail.MainId, \
+                LayerThumbnail.LayerId, \
+                LayerThumbnail.ThumbnailCanvasWidth, \
+                LayerThumbnail.ThumbnailCanvasHeight, \
+                LayerThumbnail.ThumbnailUseDrawColor, \
+                LayerThumbnail.ThumbnailMainColorRed, \
+                LayerThumbnail.ThumbnailMainColorGreen, \
+                LayerThumbnail.ThumbnailMainColorBlue, \
+                LayerThumbnail.ThumbnailSubColorRed, \
+                LayerThumbnail.ThumbnailSubColorGreen, \
+                LayerThumbnail.ThumbnailSubColorBlue, \
+                LayerThumbnail.ThumbnailOffscreen, \
+                {} \
+            FROM Layer INNER JOIN LayerThumbnail ON LayerThumbnail.MainId = Layer.LayerRenderThumbnail \
+            WHERE Layer.MainId=?1",
+            refresh_columns.join(", "),
+        ));
+
+        stmt.ok()?.query_row([layer_id], |r| {
+            let channel = |i: usize| -> rusqlite::Result<u8> { Ok(color_channel(r.get::<_, i64>(i)? as u32)) };
+
+            let mut need_refresh = [true; 7];
+            for (i, refresh) in need_refresh.iter_mut().enumerate() {
+                *refresh = r.get::<_, Option<i64>>(12 + i)?.is_none_or(|n| { n != 0 });
+            }
+
+            Ok(LayerThumbnail {
+                id: r.get(0)?,
+                layer_id: r.get(1)?,
+                need_refresh,
+                canvas_width: r.get(2)?,
+                canvas_height: r.get(3)?,
+                use_draw_color: r.get::<_, i64>(4)? != 0,
+                main_color: [channel(5)?, channel(6)?, channel(7)?],
+                sub_color: [channel(8)?, channel(9)?, channel(10)?],
+                offscreen_id: r.get(11)?,
+            })
+        }).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_fill_the_offscreen() {
+        assert_eq!(ThumbnailSize::Large.region(), (0, 80, 64, 64));
+
+        let (_, y, _, h) = ThumbnailSize::Larger2x.region();
+        assert_eq!(y + h, 512);
+    }
+}
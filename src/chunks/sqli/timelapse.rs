@@ -0,0 +1,83 @@
+use crate::ClipDb;
+use crate::chunks::sqli::walk_list;
+
+// none of the sample files have a time-lapse so this follows the ParamScheme descriptions
+// TimeLapseManager -> TimeLapseRecord (one per encoder session?) -> TimeLapseBlob (exta data)
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TimeLapseBlob {
+    pub id: i64,
+    pub offset: i64, // position in the recording stream?
+    pub size: i64,
+    pub compressed_size: i64, // zlib'd when it differs from size?
+    pub kind: i64,
+    pub exta_offset: Option<i64>, // BlobData
+}
+
+impl TimeLapseBlob {
+    pub fn is_compressed(&self) -> bool {
+        self.compressed_size > 0 && self.compressed_size != self.size
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct TimeLapseRecord {
+    pub id: i64,
+    pub encoder_name: String,
+    pub encoder_sequence: i64,
+    pub blobs: Vec<TimeLapseBlob>,
+}
+
+impl<'a> ClipDb<'a> {
+    fn get_timelapse_blobs(&self, first_id: i64) -> Vec<TimeLapseBlob> {
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                TimeLapseBlob.MainId, BlobOffset, BlobSize, BlobSizeCompressed, BlobType, ExternalChunk.Offset, NextIndex \
+            FROM TimeLapseBlob \
+            LEFT JOIN ExternalChunk ON hex(ExternalChunk.ExternalID) = hex(TimeLapseBlob.BlobData) \
+            WHERE TimeLapseBlob.MainId=?1") else { return vec![] };
+
+        walk_list(first_id, |id| {
+            let (blob, next_id) = stmt.query_row([id], |r| {
+                let blob = TimeLapseBlob {
+                    id: r.get(0)?,
+                    offset: r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                    size: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    compressed_size: r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                    kind: r.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                    exta_offset: r.get(5)?,
+                };
+                Ok((blob, r.get::<_, Option<i64>>(6)?.unwrap_or(0)))
+            }).ok()?;
+
+            Some((blob, next_id))
+        })
+    }
+
+    /// the time-lapse recordings of the canvas in order, empty if it was never recorded
+    pub fn get_timelapse_records(&self, canvas_id: i64) -> Vec<TimeLapseRecord> {
+        let first: Option<i64> = self.conn().prepare_cached("SELECT RecordFirstIndex FROM TimeLapseManager WHERE CanvasId=?1")
+            .and_then(|mut stmt| { stmt.query_row([canvas_id], |r| { r.get(0) }) })
+            .ok().flatten();
+
+        let Some(first) = first else { return vec![] };
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                MainId, EncoderName, EncoderSequence, BlobFirstIndex, NextIndex \
+            FROM TimeLapseRecord WHERE MainId=?1") else { return vec![] };
+
+        walk_list(first, |id| {
+            let (mut record, (first_blob, next_id)) = stmt.query_row([id], |r| {
+                let record = TimeLapseRecord {
+                    id: r.get(0)?,
+                    encoder_name: r.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    encoder_sequence: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                    blobs: vec![],
+                };
+                let links = (r.get::<_, Option<i64>>(3)?.unwrap_or(0), r.get::<_, Option<i64>>(4)?.unwrap_or(0));
+                Ok((record, links))
+            }).ok()?;
+            record.blobs = self.get_timelapse_blobs(first_blob);
+
+            Some((record, next_id))
+        })
+    }
+}
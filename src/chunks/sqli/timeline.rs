@@ -0,0 +1,284 @@
+use crate::ClipDb;
+use crate::chunks::sqli::walk_list;
+
+// none of the sample files are animations (or have a Track table) so this is all based on the ParamScheme descriptions
+// the real cel specifications (which cel shows on which frame) are in TrackValueMap/TrackActionMixer, those are kept
+// as they are since there's nothing to decode them against. until they are, exposures fall back to the labels of the
+// animation folder's track, which only matches CSP for files where every cel change has a label named after the cel
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct AnimationCutBank {
+    pub id: i64,
+    pub first_timeline_id: i64,
+    pub first_scenario_id: i64,
+    pub current_index: i64, // index of the open timeline?
+    pub enabled: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimelineLabel {
+    pub id: i64,
+    pub track_id: i64,
+    pub name: String,
+    pub frame: f64,
+    pub kind: i64,
+    pub length: f64, // 0 when it lasts until the next label?
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Track {
+    pub id: i64,
+    pub kind: i64,
+    pub layer_uuid: Option<String>, // LayerUuidWithTrack, the layer (animation folder) the track is for
+    pub labels: Vec<TimelineLabel>,
+    pub value_map: Vec<u8>, // TrackValueMap, ?: the cel specifications, not decoded
+    pub action_mixer_exta_offset: Option<i64>, // TrackActionMixer (ClipExtaBody::Track), not decoded
+}
+
+/// a cel shown for `length` frames from `frame`, according to a track label (not the decoded track data)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CelExposure {
+    pub frame: i64,
+    pub length: i64,
+    pub cel: String, // name of the layer/folder inside the animation folder, empty for no cel
+}
+
+impl Track {
+    /// whether the track has cel data of its own, which the label fallback ignores
+    pub fn has_track_data(&self) -> bool {
+        !self.value_map.is_empty() || self.action_mixer_exta_offset.is_some()
+    }
+
+    /// fallback for the undecoded track data: exposures guessed from the track labels in frame order, labels without a length last until the next one or `end_frame`
+    pub fn label_exposures(&self, end_frame: i64) -> Vec<CelExposure> {
+        let mut labels: Vec<&TimelineLabel> = self.labels.iter().collect();
+        labels.sort_by(|a, b| { a.frame.total_cmp(&b.frame) });
+
+        labels.iter().enumerate().map(|(i, l)| {
+            let frame = l.frame.round() as i64;
+            let until = labels.get(i + 1).map_or(end_frame + 1, |n| { n.frame.round() as i64 });
+            let length = if l.length > 0.0 { l.length.round() as i64 } else { until - frame };
+
+            CelExposure { frame, length: length.max(0), cel: l.name.clone() }
+        }).collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Timeline {
+    pub id: i64,
+    pub name: String,
+    pub frame_rate: f64,
+    pub start_frame: f64,
+    pub end_frame: f64,
+    pub current_frame: f64,
+    pub labels: Vec<TimelineLabel>,
+    pub tracks: Vec<Track>,
+}
+
+impl Timeline {
+    pub fn frames(&self) -> std::ops::RangeInclusive<i64> {
+        self.start_frame.round() as i64..=self.end_frame.round() as i64
+    }
+
+    /// how long a frame is shown in seconds
+    pub fn frame_duration(&self) -> f64 {
+        if self.frame_rate > 0.0 { 1.0 / self.frame_rate } else { 1.0 / 24.0 }
+    }
+
+    /// the track for the animation folder with the given layer uuid
+    pub fn track_for_layer(&self, layer_uuid: &str) -> Option<&Track> {
+        self.tracks.iter().find(|t| { t.layer_uuid.as_deref() == Some(layer_uuid) })
+    }
+
+    /// the label exposure of the animation folder covering the given frame (see `Track::label_exposures`)
+    /// None if it has no track or nothing is exposed
+    pub fn label_exposure_at(&self, layer_uuid: &str, frame: i64) -> Option<CelExposure> {
+        let track = self.track_for_layer(layer_uuid)?;
+        let exposures = track.label_exposures(self.end_frame.round() as i64);
+
+        exposures.into_iter().rev().find(|e| { e.frame <= frame }).filter(|e| { frame < e.frame + e.length })
+    }
+
+    /// the cel the track labels of the animation folder name for the given frame (empty for none), this is the
+    /// fallback used while the track data isn't decoded
+    /// None if the folder has no track or the track has no labels, the labels can't say anything about those
+    pub fn label_cel_at(&self, layer_uuid: &str, frame: i64) -> Option<String> {
+        self.track_for_layer(layer_uuid).filter(|t| { !t.labels.is_empty() })?;
+        Some(self.label_exposure_at(layer_uuid, frame).map(|e| { e.cel }).unwrap_or_default())
+    }
+}
+
+// uuids in blob columns, either plain or a utf16 string with a u32 char count
+pub(crate) fn blob_text(b: &[u8]) -> String {
+    if b.len() >= 4 && u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize * 2 == b.len() - 4 {
+        let chars: Vec<u16> = b[4..].chunks_exact(2).map(|c| { u16::from_be_bytes([c[0], c[1]]) }).collect();
+        return String::from_utf16_lossy(&chars);
+    }
+
+    String::from_utf8_lossy(b).trim_end_matches('\0').to_string()
+}
+
+impl<'a> ClipDb<'a> {
+    pub fn get_animation_cut_bank(&self) -> Option<AnimationCutBank> {
+        let stmt = self.conn().prepare_cached("SELECT MainId, FirstTimeLine, FirstScenario, CurrentIndex, Enable FROM AnimationCutBank");
+
+        stmt.ok()?.query_row([], |r| {
+            Ok(AnimationCutBank {
+                id: r.get(0)?,
+                first_timeline_id: r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                first_scenario_id: r.get::<_, Option<i64>>(2)?.unwrap_or(0),
+                current_index: r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                enabled: r.get::<_, Option<i64>>(4)?.unwrap_or(0) != 0,
+            })
+        }).ok()
+    }
+
+    // follows a linked list of labels
+    fn get_timeline_labels(&self, first_id: i64) -> Vec<TimelineLabel> {
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                MainId, TrackId, LabelName, LabelFrame, LabelType, LabelLength, LabelNextIndex \
+            FROM TimeLineLabel WHERE MainId=?1") else { return vec![] };
+
+        walk_list(first_id, |id| {
+            let (label, next_id) = stmt.query_row([id], |r| {
+                let label = TimelineLabel {
+                    id: r.get(0)?,
+                    track_id: r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                    name: r.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    frame: r.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+                    kind: r.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                    length: r.get::<_, Option<f64>>(5)?.unwrap_or(0.0),
+                };
+                Ok((label, r.get::<_, Option<i64>>(6)?.unwrap_or(0)))
+            }).ok()?;
+
+            Some((label, next_id))
+        })
+    }
+
+    fn get_tracks(&self, first_id: i64) -> Vec<Track> {
+        let Ok(mut stmt) = self.conn().prepare_cached(&format!("SELECT \
+                Track.MainId, TrackKind, LayerUuidWithTrack, TrackLabelFirstIndex, {}, ExternalChunk.Offset, TrackNextIndex \
+            FROM Track \
+            LEFT JOIN ExternalChunk ON hex(ExternalChunk.ExternalID) = hex({}) \
+            WHERE Track.MainId=?1",
+            self.column_or_null("Track", "TrackValueMap"),
+            self.column_or_null("Track", "TrackActionMixer"),
+        )) else { return vec![] };
+
+        walk_list(first_id, |id| {
+            let ((track_id, kind, layer_uuid, first_label, value_map, action_mixer_exta_offset), next_id) = stmt.query_row([id], |r| {
+                let uuid: Option<Vec<u8>> = r.get(2)?;
+                let track = (
+                    r.get::<_, i64>(0)?,
+                    r.get::<_, Option<i64>>(1)?.unwrap_or(0),
+                    uuid.map(|b| { blob_text(&b) }).filter(|u| { !u.is_empty() }),
+                    r.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                    r.get::<_, Option<Vec<u8>>>(4)?.unwrap_or_default(),
+                    r.get::<_, Option<i64>>(5)?,
+                );
+                Ok((track, r.get::<_, Option<i64>>(6)?.unwrap_or(0)))
+            }).ok()?;
+            let labels = self.get_timeline_labels(first_label);
+
+            Some((Track { id: track_id, kind, layer_uuid, labels, value_map, action_mixer_exta_offset }, next_id))
+        })
+    }
+
+    /// all timelines (animation cuts) in the file, in order
+    pub fn get_timelines(&self) -> Vec<Timeline> {
+        let Some(bank) = self.get_animation_cut_bank() else { return vec![] };
+        let Ok(mut stmt) = self.conn().prepare_cached("SELECT \
+                MainId, TimeLineName, FrameRate, StartFrame, EndFrame, CurrentFrame, LabelFirstIndex, FirstTrack, NextTimeLine \
+            FROM TimeLine WHERE MainId=?1") else { return vec![] };
+
+        walk_list(bank.first_timeline_id, |id| {
+            let (mut timeline, (first_label, first_track), next_id) = stmt.query_row([id], |r| {
+                let timeline = Timeline {
+                    id: r.get(0)?,
+                    name: r.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    frame_rate: r.get::<_, Option<f64>>(2)?.unwrap_or(24.0),
+                    start_frame: r.get::<_, Option<f64>>(3)?.unwrap_or(1.0),
+                    end_frame: r.get::<_, Option<f64>>(4)?.unwrap_or(1.0),
+                    current_frame: r.get::<_, Option<f64>>(5)?.unwrap_or(1.0),
+                    labels: vec![],
+                    tracks: vec![],
+                };
+                let links = (r.get::<_, Option<i64>>(6)?.unwrap_or(0), r.get::<_, Option<i64>>(7)?.unwrap_or(0));
+                Ok((timeline, links, r.get::<_, Option<i64>>(8)?.unwrap_or(0)))
+            }).ok()?;
+            timeline.labels = self.get_timeline_labels(first_label);
+            timeline.tracks = self.get_tracks(first_track);
+
+            Some((timeline, next_id))
+        })
+    }
+
+    /// the timeline that's open in CSP (CurrentIndex), or the first one
+    pub fn get_current_timeline(&self) -> Option<Timeline> {
+        let bank = self.get_animation_cut_bank()?;
+        let mut timelines = self.get_timelines();
+
+        let index = (bank.current_index.max(0) as usize).min(timelines.len().checked_sub(1)?);
+        Some(timelines.swap_remove(index))
+    }
+
+    /// IDs of the animation folders in the canvas, oldest first
+    pub fn get_animation_folders(&self, canvas_id: i64) -> Vec<i64> {
+        if !self.column_exists("Layer", "AnimationFolder") {
+            return vec![];
+        }
+
+        let stmt = self.conn().prepare_cached("SELECT MainId FROM Layer WHERE CanvasId=?1 AND AnimationFolder != 0 ORDER BY MainId");
+        let Ok(mut stmt) = stmt else { return vec![] };
+
+        stmt.query_map([canvas_id], |r| {
+            r.get(0)
+        }).map_or(vec![], |rows| { rows.filter_map(|r| { r.ok() }).collect() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(name: &str, frame: f64, length: f64) -> TimelineLabel {
+        TimelineLabel { id: 0, track_id: 1, name: name.to_string(), frame, kind: 0, length }
+    }
+
+    fn timeline(labels: Vec<TimelineLabel>) -> Timeline {
+        let track = Track { id: 1, kind: 1, layer_uuid: Some("folder".to_string()), labels, value_map: vec![], action_mixer_exta_offset: None };
+        Timeline {
+            id: 1,
+            name: String::new(),
+            frame_rate: 12.0,
+            start_frame: 1.0,
+            end_frame: 6.0,
+            current_frame: 1.0,
+            labels: vec![],
+            tracks: vec![track],
+        }
+    }
+
+    #[test]
+    fn labels_last_until_the_next_one() {
+        let t = timeline(vec![label("b", 4.0, 0.0), label("a", 1.0, 2.0)]);
+        let exposures = t.tracks[0].label_exposures(6);
+
+        assert_eq!(exposures, vec![
+            CelExposure { frame: 1, length: 2, cel: "a".to_string() },
+            CelExposure { frame: 4, length: 3, cel: "b".to_string() },
+        ]);
+
+        assert_eq!(t.label_cel_at("folder", 2).as_deref(), Some("a"));
+        assert_eq!(t.label_cel_at("folder", 3).as_deref(), Some(""));
+        assert_eq!(t.label_cel_at("folder", 6).as_deref(), Some("b"));
+        assert_eq!(t.label_cel_at("other", 1), None);
+    }
+
+    #[test]
+    fn tracks_without_labels_say_nothing() {
+        assert_eq!(timeline(vec![]).label_cel_at("folder", 1), None);
+    }
+}
@@ -0,0 +1,98 @@
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+
+use crate::{ClipFile, IccProfile, RenderingIntent, RgbaImage};
+
+/// 8 bit cmyk image (no alpha, transparent areas are flattened onto white paper)
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct CmykImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+fn load_profile(profile: Option<&IccProfile>) -> Option<ColorProfile> {
+    ColorProfile::new_from_slice(&profile?.data).ok()
+}
+
+fn transform_options(intent: Option<RenderingIntent>) -> TransformOptions {
+    let rendering_intent = match intent {
+        Some(RenderingIntent::RelativeColorimetric) => moxcms::RenderingIntent::RelativeColorimetric,
+        Some(RenderingIntent::Saturation) => moxcms::RenderingIntent::Saturation,
+        Some(RenderingIntent::AbsoluteColorimetric) => moxcms::RenderingIntent::AbsoluteColorimetric,
+        _ => moxcms::RenderingIntent::Perceptual,
+    };
+
+    TransformOptions { rendering_intent, ..Default::default() }
+}
+
+fn rgb(img: &RgbaImage) -> Vec<u8> {
+    img.data.chunks_exact(4).flat_map(|p| { [p[0], p[1], p[2]] }).collect()
+}
+
+// the canvas image flattened onto white, as 3 channel rgb
+fn flatten_rgb(img: &RgbaImage) -> Vec<u8> {
+    img.data.chunks_exact(4).flat_map(|p| {
+        let a = p[3] as u32;
+        [0, 1, 2].map(|c| { ((p[c] as u32 * a + 255 * (255 - a) + 127) / 255) as u8 })
+    }).collect()
+}
+
+// (source profile, simulated device profile, intent) for the canvas
+// the simulation source falls back to the canvas profile then srgb
+fn simulation_profiles(file: &ClipFile, canvas_id: i64) -> Option<(ColorProfile, ColorProfile, TransformOptions)> {
+    let colors = file.db().get_canvas_color_settings(canvas_id)?;
+
+    let device = load_profile(colors.simulate_dst_profile.as_ref())?;
+    let source = load_profile(colors.simulate_src_profile.as_ref())
+        .or_else(|| { load_profile(colors.src_profile.as_ref()) })
+        .unwrap_or_else(ColorProfile::new_srgb);
+
+    Some((source, device, transform_options(colors.simulate_rendering_intent)))
+}
+
+impl ClipFile {
+    /// the canvas converted to the simulation (print) profile of the canvas, None if there isn't one or it's not cmyk
+    pub fn render_cmyk(&self, canvas_id: i64, scale: f64) -> Option<CmykImage> {
+        let (source, device, options) = simulation_profiles(self, canvas_id)?;
+        if device.color_space != DataColorSpace::Cmyk {
+            return None;
+        }
+
+        let img = self.render_canvas(canvas_id, scale)?;
+        let transform = source.create_transform_8bit(Layout::Rgb, &device, Layout::Rgba, options).ok()?;
+
+        let mut data = vec![0u8; img.width as usize * img.height as usize * 4];
+        transform.transform(&flatten_rgb(&img), &mut data).ok()?;
+
+        Some(CmykImage { width: img.width, height: img.height, data })
+    }
+
+    /// soft proof of the canvas, the colours it would have after going through the simulation profile
+    /// rendered back into the source profile so it can be displayed, alpha is kept as is
+    pub fn render_soft_proof(&self, canvas_id: i64, scale: f64) -> Option<RgbaImage> {
+        let (source, device, options) = simulation_profiles(self, canvas_id)?;
+
+        let device_layout = match device.color_space {
+            DataColorSpace::Cmyk => Layout::Rgba,
+            DataColorSpace::Rgb => Layout::Rgb,
+            _ => return None,
+        };
+
+        let forward = source.create_transform_8bit(Layout::Rgb, &device, device_layout, options).ok()?;
+        let back = device.create_transform_8bit(device_layout, &source, Layout::Rgb, options).ok()?;
+
+        let mut img = self.render_canvas(canvas_id, scale)?;
+        let pixels = img.width as usize * img.height as usize;
+
+        let mut device_data = vec![0u8; pixels * device_layout.channels()];
+        let mut proofed = vec![0u8; pixels * 3];
+        forward.transform(&rgb(&img), &mut device_data).ok()?;
+        back.transform(&device_data, &mut proofed).ok()?;
+
+        for (p, c) in img.data.chunks_exact_mut(4).zip(proofed.chunks_exact(3)) {
+            p[..3].copy_from_slice(c);
+        }
+
+        Some(img)
+    }
+}
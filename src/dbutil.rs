@@ -1,6 +1,8 @@
 use std::marker::PhantomData;
-use rusqlite::{Connection, ffi};
+use std::ptr::NonNull;
+use rusqlite::{Connection, DatabaseName, ffi};
 use rusqlite::Error::SqliteFailure;
+use rusqlite::serialize::OwnedData;
 
 pub struct BorrowedConnection<'a> {
     pub conn: Connection,
@@ -30,3 +32,23 @@ impl<'a> BorrowedConnection<'a> {
         Ok(Self { conn, data: PhantomData })
     }
 }
+
+/// opens a read only connection to a copy of the given sqlite data
+pub fn copied_connection(data: &[u8]) -> Result<Connection, rusqlite::Error> {
+    let mut conn = Connection::open_in_memory()?;
+
+    // SAFETY: the buffer is allocated by sqlite with the right size and ownership is passed
+    // to the connection, which frees it on close
+    let owned = unsafe {
+        let p = ffi::sqlite3_malloc64(data.len() as _) as *mut u8;
+        NonNull::new(p).map(|p| {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), p.as_ptr(), data.len());
+            OwnedData::from_raw_nonnull(p, data.len())
+        })
+    };
+
+    let owned = owned.ok_or(SqliteFailure(ffi::Error::new(ffi::SQLITE_NOMEM), None))?;
+    conn.deserialize(DatabaseName::Main, owned, true)?;
+
+    Ok(conn)
+}
@@ -0,0 +1,239 @@
+use gif::{DisposalMethod, Repeat};
+use image_webp::{ColorType, WebPEncoder};
+use png::{BlendOp, DisposeOp, Encoder};
+
+use crate::{ClipFile, RgbaImage, Timeline};
+use crate::export::png::png_info;
+
+/// a rendered frame of an animation and how many timeline frames it's shown for
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub frame: i64,
+    pub length: u32,
+}
+
+// rendered frames with consecutive identical ones (held cels) merged
+struct Animation {
+    frames: Vec<AnimationFrame>,
+    frame_rate: f64,
+}
+
+impl Animation {
+    fn delay_ms(&self, frame: &AnimationFrame) -> u32 {
+        (frame.length as f64 * 1000.0 / self.frame_rate).round() as u32
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.frames.first().map_or((0, 0), |f| { (f.image.width, f.image.height) })
+    }
+
+    // None for empty frames, webp stores sizes minus one
+    fn to_webp(&self, icc: Option<&[u8]>) -> Option<Vec<u8>> {
+        let (width, height) = self.size();
+        if !(1..=1 << 24).contains(&width) || !(1..=1 << 24).contains(&height) {
+            return None;
+        }
+
+        let mut body = b"WEBP".to_vec();
+
+        // VP8X: animation + alpha (+ icc) flags and the canvas size
+        let mut vp8x = vec![0x02 | 0x10 | if icc.is_some() { 0x20 } else { 0 }, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(width - 1));
+        vp8x.extend_from_slice(&u24(height - 1));
+        webp_chunk(&mut body, b"VP8X", &vp8x);
+
+        if let Some(icc) = icc {
+            webp_chunk(&mut body, b"ICCP", icc);
+        }
+
+        // transparent background, loop forever
+        webp_chunk(&mut body, b"ANIM", &[0, 0, 0, 0, 0, 0]);
+
+        for frame in &self.frames {
+            let mut still = vec![];
+            WebPEncoder::new(&mut still).encode(&frame.image.data, width, height, ColorType::Rgba8).ok()?;
+
+            // the simple format is RIFF size WEBP followed by the VP8L chunk
+            let vp8l = still.get(12..)?;
+
+            for delay in split_delay(self.delay_ms(frame), 0xffffff) {
+                // offset (0, 0), size, duration, flags: no blending, no disposal
+                let mut anmf = vec![0; 6];
+                anmf.extend_from_slice(&u24(width - 1));
+                anmf.extend_from_slice(&u24(height - 1));
+                anmf.extend_from_slice(&u24(delay));
+                anmf.push(0x02);
+
+                anmf.extend_from_slice(vp8l);
+                webp_chunk(&mut body, b"ANMF", &anmf);
+            }
+        }
+
+        let mut out = b"RIFF".to_vec();
+        out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        out.extend_from_slice(&body);
+
+        Some(out)
+    }
+}
+
+// a frame delay in parts of at most `max`, formats with small delay fields show long holds as repeated frames
+fn split_delay(delay: u32, max: u32) -> impl Iterator<Item = u32> {
+    let parts = delay.div_ceil(max).max(1);
+    (0..parts).map(move |i| { if i + 1 < parts { max } else { delay - max * (parts - 1) } })
+}
+
+// riff chunk with its padding byte
+fn webp_chunk(out: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(tag);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+fn u24(v: u32) -> [u8; 3] {
+    let [a, b, c, _] = v.to_le_bytes();
+    [a, b, c]
+}
+
+impl ClipFile {
+    fn render_animation(&self, canvas_id: i64, scale: f64) -> Option<Animation> {
+        let timeline = self.db().get_current_timeline()?;
+        let frames = self.animation_frames(canvas_id, &timeline, scale)?;
+        let frame_rate = if timeline.frame_rate > 0.0 { timeline.frame_rate } else { 24.0 };
+
+        Some(Animation { frames, frame_rate })
+    }
+
+    /// renders every frame of the timeline, frames that look the same as the previous one are merged into it
+    /// cels are picked from the track labels like `render_frame` (the track data fallback)
+    pub fn animation_frames(&self, canvas_id: i64, timeline: &Timeline, scale: f64) -> Option<Vec<AnimationFrame>> {
+        let mut frames: Vec<AnimationFrame> = vec![];
+
+        for frame in timeline.frames() {
+            let image = self.render_timeline_frame(canvas_id, timeline, frame, scale)?;
+
+            match frames.last_mut() {
+                Some(last) if last.image == image => last.length += 1,
+                _ => frames.push(AnimationFrame { image, frame, length: 1 }),
+            }
+        }
+
+        (!frames.is_empty()).then_some(frames)
+    }
+
+    /// the current timeline as a looping apng, tagged like `export_canvas_png`
+    pub fn export_apng(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let anim = self.render_animation(canvas_id, scale)?;
+        let (width, height) = anim.size();
+        let metadata = self.image_metadata(canvas_id, scale);
+
+        // exact when the frame rate is a whole number, otherwise in milliseconds
+        let whole = anim.frame_rate.fract() == 0.0 && anim.frame_rate <= u16::MAX as f64;
+        let denominator = if whole { anim.frame_rate as u16 } else { 1000 };
+
+        let frames: Vec<(&AnimationFrame, u32)> = anim.frames.iter().flat_map(|frame| {
+            let delay = if whole { frame.length } else { anim.delay_ms(frame) };
+            split_delay(delay, u16::MAX as u32).map(move |d| { (frame, d) })
+        }).collect();
+
+        let mut out = vec![];
+        let mut encoder = Encoder::with_info(&mut out, png_info(width, height, &metadata)).ok()?;
+        encoder.set_animated(u32::try_from(frames.len()).ok()?, 0).ok()?;
+
+        let mut writer = encoder.write_header().ok()?;
+        writer.set_blend_op(BlendOp::Source).ok()?;
+        writer.set_dispose_op(DisposeOp::None).ok()?;
+
+        for (frame, delay) in frames {
+            writer.set_frame_delay(delay as u16, denominator).ok()?;
+            writer.write_image_data(&frame.image.data).ok()?;
+        }
+
+        writer.finish().ok()?;
+        Some(out)
+    }
+
+    /// the current timeline as a looping gif, colours are quantized per frame and only fully transparent pixels stay transparent
+    /// None if the canvas is too big for a gif (65535px)
+    pub fn export_gif(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let anim = self.render_animation(canvas_id, scale)?;
+        let (width, height) = anim.size();
+        let (width, height) = (u16::try_from(width).ok()?, u16::try_from(height).ok()?);
+
+        let mut out = vec![];
+        {
+            let mut encoder = gif::Encoder::new(&mut out, width, height, &[]).ok()?;
+            encoder.set_repeat(Repeat::Infinite).ok()?;
+
+            for frame in &anim.frames {
+                let mut data = frame.image.data.clone();
+                let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut data, 10);
+                gif_frame.dispose = DisposalMethod::Background;
+
+                // in centiseconds, most viewers slow down anything under 2
+                let delay = ((anim.delay_ms(frame) as f64 / 10.0).round() as u32).max(2);
+
+                for part in split_delay(delay, u16::MAX as u32) {
+                    gif_frame.delay = part as u16;
+                    encoder.write_frame(&gif_frame).ok()?;
+                }
+            }
+        }
+
+        Some(out)
+    }
+
+    /// the current timeline as a looping lossless animated webp, with the source icc profile if there is one
+    pub fn export_webp(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let anim = self.render_animation(canvas_id, scale)?;
+        let icc = self.image_metadata(canvas_id, scale).icc_profile;
+
+        anim.to_webp(icc.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(width: u32, height: u32) -> Animation {
+        let frame = AnimationFrame { image: RgbaImage::new(width, height), frame: 1, length: 2 };
+        Animation { frames: vec![frame], frame_rate: 12.0 }
+    }
+
+    #[test]
+    fn empty_webp_is_rejected() {
+        assert!(Animation { frames: vec![], frame_rate: 12.0 }.to_webp(None).is_none());
+        assert!(animation(0, 0).to_webp(None).is_none());
+        assert!(animation(4, 0).to_webp(None).is_none());
+    }
+
+    #[test]
+    fn long_delays_are_split() {
+        assert_eq!(split_delay(10, 4).collect::<Vec<_>>(), vec![4, 4, 2]);
+        assert_eq!(split_delay(8, 4).collect::<Vec<_>>(), vec![4, 4]);
+        assert_eq!(split_delay(0, 4).collect::<Vec<_>>(), vec![0]);
+
+        // 0xffffff ms is a bit over 4.6 hours, this frame is held for twice that
+        let mut anim = animation(1, 1);
+        anim.frames[0].length = 0xffffff / 1000 * 2 + 1;
+        anim.frame_rate = 1.0;
+        let webp = anim.to_webp(None).unwrap();
+        assert_eq!(webp.windows(4).filter(|w| { *w == b"ANMF" }).count(), 3);
+    }
+
+    #[test]
+    fn webp_header() {
+        let webp = animation(3, 2).to_webp(None).unwrap();
+        assert_eq!(&webp[..4], b"RIFF");
+        assert_eq!(&webp[8..16], b"WEBPVP8X");
+        assert_eq!(u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize, webp.len() - 8);
+
+        // canvas size minus one
+        assert_eq!(&webp[24..30], &[2, 0, 0, 1, 0, 0]);
+    }
+}
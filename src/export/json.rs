@@ -0,0 +1,27 @@
+// hand written json is small enough that it's not worth pulling in serde
+
+/// a quoted json string
+pub(crate) fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+/// a json number, non finite values (which json can't represent) become null
+pub(crate) fn number(v: f64) -> String {
+    if v.is_finite() { format!("{v}") } else { "null".to_string() }
+}
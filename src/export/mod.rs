@@ -0,0 +1,9 @@
+pub(crate) mod animation;
+mod json;
+pub(crate) mod patterns;
+pub(crate) mod png;
+mod rulers;
+mod sequence;
+mod svg;
+mod tiff;
+pub(crate) mod xsheet;
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use png::{BitDepth, ColorType, Encoder, EncodingError};
+
+use crate::export::json;
+use crate::{BrushPatternImage, ClipFile, RgbaImage};
+
+/// a decoded brush tip or texture
+#[derive(Debug, Clone)]
+pub struct PatternImage {
+    pub pattern: BrushPatternImage,
+    pub image: RgbaImage,
+    pub gray: Option<Vec<u8>>, // see RgbaImage::to_gray
+}
+
+impl PatternImage {
+    /// a grayscale png if the pattern has no colour, rgba otherwise
+    pub fn to_png(&self) -> Result<Vec<u8>, EncodingError> {
+        let Some(gray) = &self.gray else { return self.image.to_png() };
+
+        let mut out = vec![];
+        let mut encoder = Encoder::new(&mut out, self.image.width, self.image.height);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(gray)?;
+        writer.finish()?;
+
+        Ok(out)
+    }
+}
+
+fn ids(ids: &[i64]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| { id.to_string() }).collect();
+    format!("[{}]", ids.join(", "))
+}
+
+impl ClipFile {
+    /// the brush tips and textures embedded in the canvas, patterns that fail to decode are left out
+    pub fn brush_patterns(&self, canvas_id: i64) -> Vec<PatternImage> {
+        let db = self.db();
+        let Some(manager_id) = db.get_canvas(canvas_id).and_then(|c| { c.brush_style_manager_id }) else { return vec![] };
+
+        db.get_brush_pattern_images(manager_id).into_iter().filter_map(|pattern| {
+            let image = self.render_brush_pattern(&pattern)?;
+            Some(PatternImage { gray: image.to_gray(), pattern, image })
+        }).collect()
+    }
+
+    /// writes every brush pattern as a png (`pattern_0001.png` named after the pattern id) into `dir`
+    /// along with `patterns.json`, listing the patterns and the tips and texture each brush uses
+    pub fn export_brush_patterns(&self, canvas_id: i64, dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let patterns = self.brush_patterns(canvas_id);
+        if patterns.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "No brush patterns"));
+        }
+
+        std::fs::create_dir_all(&dir)?;
+        let mut paths = vec![];
+        let mut entries = vec![];
+
+        for p in &patterns {
+            let name = format!("pattern_{:04}.png", p.pattern.id);
+            let path = dir.as_ref().join(&name);
+
+            std::fs::write(&path, p.to_png()?)?;
+            paths.push(path);
+
+            entries.push(format!(
+                "    {{\"id\": {}, \"name\": {}, \"uuid\": {}, \"file\": {}, \"width\": {}, \"height\": {}, \"grayscale\": {}}}",
+                p.pattern.id,
+                json::string(&p.pattern.name),
+                json::string(&p.pattern.uuid),
+                json::string(&name),
+                p.image.width,
+                p.image.height,
+                p.gray.is_some(),
+            ));
+        }
+
+        let db = self.db();
+        let brushes: Vec<String> = db.get_brush_styles_for_canvas(canvas_id).iter().map(|b| {
+            let tips = db.get_brush_pattern_style(b.pattern_style_id).map(|s| { s.image_ids }).unwrap_or_default();
+            let texture = if b.texture.pattern_id != 0 { b.texture.pattern_id.to_string() } else { "null".to_string() };
+
+            format!("    {{\"id\": {}, \"tips\": {}, \"texture\": {texture}}}", b.id, ids(&tips))
+        }).collect();
+
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"patterns\": [\n{}\n  ],", entries.join(",\n")).unwrap();
+        if brushes.is_empty() {
+            out.push_str("  \"brushes\": []\n");
+        } else {
+            writeln!(out, "  \"brushes\": [\n{}\n  ]", brushes.join(",\n")).unwrap();
+        }
+        out.push_str("}\n");
+
+        std::fs::write(dir.as_ref().join("patterns.json"), out)?;
+        Ok(paths)
+    }
+}
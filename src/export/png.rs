@@ -0,0 +1,80 @@
+use std::borrow::Cow;
+
+use png::{BitDepth, ColorType, Encoder, EncodingError, Info, PixelDimensions, Unit};
+
+use crate::{ClipFile, RgbaImage};
+
+/// extra info written along with exported images
+#[derive(Debug, Default, Clone)]
+pub struct ImageMetadata {
+    pub icc_profile: Option<Vec<u8>>,
+    pub dpi: Option<f64>,
+}
+
+// 8 bit rgba header info with the metadata chunks
+pub(crate) fn png_info(width: u32, height: u32, metadata: &ImageMetadata) -> Info<'_> {
+    let mut info = Info::with_size(width, height);
+    info.color_type = ColorType::Rgba;
+    info.bit_depth = BitDepth::Eight;
+    info.icc_profile = metadata.icc_profile.as_deref().map(Cow::Borrowed);
+    info.pixel_dims = metadata.dpi.map(|dpi| {
+        let ppm = (dpi / 0.0254).round() as u32;
+        PixelDimensions { xppu: ppm, yppu: ppm, unit: Unit::Meter }
+    });
+
+    info
+}
+
+impl RgbaImage {
+    pub fn to_png(&self) -> Result<Vec<u8>, EncodingError> {
+        self.to_png_with(&ImageMetadata::default())
+    }
+
+    /// encodes the image as a png with the given metadata (iCCP chunk for the icc profile, pHYs for the dpi)
+    /// fails for empty images
+    pub fn to_png_with(&self, metadata: &ImageMetadata) -> Result<Vec<u8>, EncodingError> {
+        let mut out = vec![];
+        let mut writer = Encoder::with_info(&mut out, png_info(self.width, self.height, metadata))?.write_header()?;
+        writer.write_image_data(&self.data)?;
+        writer.finish()?;
+
+        Ok(out)
+    }
+}
+
+impl ClipFile {
+    /// metadata for images exported from the given canvas at the given scale
+    /// the resolution is scaled too so the physical size stays the same
+    pub fn image_metadata(&self, canvas_id: i64, scale: f64) -> ImageMetadata {
+        let colors = self.db().get_canvas_color_settings(canvas_id);
+        let canvas = self.db().get_canvas(canvas_id);
+
+        ImageMetadata {
+            icc_profile: colors.and_then(|c| { c.src_profile }).map(|p| { p.data }),
+            dpi: canvas.map(|c| { c.resolution_dpi * scale }).filter(|dpi| { *dpi > 0.0 }),
+        }
+    }
+
+    /// the flattened canvas as a png, tagged with the canvas' resolution and colour profile if it has one
+    pub fn export_canvas_png(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let img = self.render_canvas(canvas_id, scale)?;
+        img.to_png_with(&self.image_metadata(canvas_id, scale)).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_image_is_an_error() {
+        assert!(RgbaImage::new(0, 0).to_png().is_err());
+        assert!(RgbaImage::new(0, 4).to_png().is_err());
+    }
+
+    #[test]
+    fn png_signature() {
+        let png = RgbaImage::new(2, 3).to_png_with(&ImageMetadata { icc_profile: None, dpi: Some(300.0) }).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+
+use crate::export::json;
+use crate::{CanvasUnit, ClipFile, SpecialRuler, SpecialRulerKind, VanishingPoint};
+
+fn point((x, y): (f64, f64)) -> String {
+    format!("[{}, {}]", json::number(x), json::number(y))
+}
+
+fn points(pts: &[(f64, f64)]) -> String {
+    let pts: Vec<String> = pts.iter().map(|&p| { point(p) }).collect();
+    format!("[{}]", pts.join(", "))
+}
+
+// one item per line, or an empty list
+fn lines(items: &[String], indent: &str) -> String {
+    if items.is_empty() { "[]".to_string() } else { format!("[\n{}\n{indent}]", items.join(",\n")) }
+}
+
+fn vanishing_point(v: &VanishingPoint) -> String {
+    format!(
+        "{{\"id\": {}, \"point\": {}, \"parallel\": {}, \"parallel_angle\": {}, \"guides\": {}}}",
+        v.id, point(v.point), v.is_parallel(), json::number(v.parallel_angle), points(&v.guides)
+    )
+}
+
+// the kind specific fields of a ruler, without braces
+fn geometry(kind: &SpecialRulerKind) -> String {
+    match kind {
+        SpecialRulerKind::Parallel { center, angle } => {
+            format!("\"center\": {}, \"angle\": {}", point(*center), json::number(*angle))
+        }
+        SpecialRulerKind::CurveParallel { curve_kind, points: pts } | SpecialRulerKind::CurveEmit { curve_kind, points: pts } => {
+            format!("\"curve_kind\": {curve_kind}, \"points\": {}", points(pts))
+        }
+        SpecialRulerKind::MultiCurve { curve_kind, center, offset_angle, points: pts } => format!(
+            "\"curve_kind\": {curve_kind}, \"center\": {}, \"offset_angle\": {}, \"points\": {}",
+            point(*center), json::number(*offset_angle), points(pts)
+        ),
+        SpecialRulerKind::Emit { center } => format!("\"center\": {}", point(*center)),
+        SpecialRulerKind::ConcentricCircle { center, radius, angle } => format!(
+            "\"center\": {}, \"radius\": {}, \"angle\": {}",
+            point(*center), point(*radius), json::number(*angle)
+        ),
+        SpecialRulerKind::Guide { horizontal, position } => {
+            format!("\"horizontal\": {horizontal}, \"position\": {}", point(*position))
+        }
+        SpecialRulerKind::Perspective(p) => {
+            let vps: Vec<String> = p.vanishing_points.iter().map(vanishing_point).collect();
+            format!(
+                "\"perspective_type\": {}, \"eye_level\": {}, \"vanishing_points\": [{}]",
+                p.kind, point(p.eye_level_handle), vps.join(", ")
+            )
+        }
+        SpecialRulerKind::Symmetry { center, angle, lines, line_symmetry } => format!(
+            "\"center\": {}, \"angle\": {}, \"lines\": {lines}, \"line_symmetry\": {line_symmetry}",
+            point(*center), json::number(*angle)
+        ),
+    }
+}
+
+fn ruler(r: &SpecialRuler) -> String {
+    format!(
+        "{{\"id\": {}, \"kind\": {}, \"snap\": {}, {}}}",
+        r.id, json::string(r.kind.name()), r.snap, geometry(&r.kind)
+    )
+}
+
+fn unit_name(unit: CanvasUnit) -> &'static str {
+    match unit {
+        CanvasUnit::Pixels => "px",
+        CanvasUnit::Centimetres => "cm",
+        CanvasUnit::Millimetres => "mm",
+        CanvasUnit::Inches => "in",
+        CanvasUnit::Points => "pt",
+        CanvasUnit::Unknown(_) => "unknown",
+    }
+}
+
+impl ClipFile {
+    /// the special rulers of the canvas as json, grouped by the layer they belong to (positions in canvas pixels)
+    pub fn export_rulers_json(&self, canvas_id: i64) -> Option<String> {
+        let db = self.db();
+        let canvas = db.get_canvas(canvas_id)?;
+
+        let layers: Vec<String> = db.get_layer_ids_for_canvas(canvas_id).into_iter().filter_map(|id| {
+            let layer = db.get_layer(id)?;
+            let rulers = db.get_special_rulers(layer.special_ruler_manager_id?);
+            if rulers.is_empty() {
+                return None;
+            }
+
+            let rulers: Vec<String> = rulers.iter().map(|r| { format!("        {}", ruler(r)) }).collect();
+            Some(format!(
+                "    {{\"id\": {}, \"name\": {}, \"rulers\": [\n{}\n      ]}}",
+                layer.id, json::string(&layer.name), rulers.join(",\n")
+            ))
+        }).collect();
+
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"width\": {},", json::number(canvas.width)).unwrap();
+        writeln!(out, "  \"height\": {},", json::number(canvas.height)).unwrap();
+        writeln!(out, "  \"layers\": {}", lines(&layers, "  ")).unwrap();
+        out.push_str("}\n");
+
+        Some(out)
+    }
+
+    /// the guides of the canvas as json, positions in pixels, millimetres and the canvas' display unit
+    pub fn export_guides_json(&self, canvas_id: i64) -> Option<String> {
+        let canvas = self.db().get_canvas(canvas_id)?;
+        let dpi = canvas.resolution_dpi;
+        let convert = |v: Option<f64>| { v.map_or("null".to_string(), json::number) };
+
+        let guides: Vec<String> = self.guides(canvas_id).iter().map(|g| {
+            format!(
+                "    {{\"id\": {}, \"layer\": {}, \"orientation\": {}, \"position_px\": {}, \"position_mm\": {}, \"position\": {}, \"snap\": {}}}",
+                g.id,
+                g.layer_id,
+                json::string(if g.horizontal { "horizontal" } else { "vertical" }),
+                json::number(g.position),
+                convert(g.position_in(CanvasUnit::Millimetres, dpi)),
+                convert(g.position_in(canvas.unit, dpi)),
+                g.snap,
+            )
+        }).collect();
+
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"width\": {},", json::number(canvas.width)).unwrap();
+        writeln!(out, "  \"height\": {},", json::number(canvas.height)).unwrap();
+        writeln!(out, "  \"resolution_dpi\": {},", json::number(dpi)).unwrap();
+        writeln!(out, "  \"unit\": {},", json::string(unit_name(canvas.unit))).unwrap();
+        writeln!(out, "  \"guides\": {}", lines(&guides, "  ")).unwrap();
+        out.push_str("}\n");
+
+        Some(out)
+    }
+}
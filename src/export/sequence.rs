@@ -0,0 +1,28 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::ClipFile;
+
+impl ClipFile {
+    /// renders every frame of the current timeline (see `render_frame`) as a png (`frame_0001.png` etc.) into `dir`
+    /// returns the written paths in frame order
+    pub fn export_frame_sequence(&self, canvas_id: i64, scale: f64, dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let not_found = |msg: &str| { io::Error::new(io::ErrorKind::NotFound, msg) };
+
+        let timeline = self.db().get_current_timeline().ok_or_else(|| { not_found("No animation timeline") })?;
+        let metadata = self.image_metadata(canvas_id, scale);
+
+        std::fs::create_dir_all(&dir)?;
+        let mut paths = vec![];
+
+        for frame in timeline.frames() {
+            let img = self.render_timeline_frame(canvas_id, &timeline, frame, scale).ok_or_else(|| { not_found("Canvas not found") })?;
+
+            let path = dir.as_ref().join(format!("frame_{frame:04}.png"));
+            std::fs::write(&path, img.to_png_with(&metadata)?)?;
+            paths.push(path);
+        }
+
+        Ok(paths)
+    }
+}
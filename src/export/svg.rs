@@ -0,0 +1,161 @@
+use std::fmt::Write;
+
+use crate::{ClipFile, Layer};
+use crate::chunks::sqli::walk_list;
+use crate::render::overlay::{ruler_shapes, OverlayShape};
+use crate::vector::{stroke_opacity, stroke_outline};
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn path_data(polys: &[Vec<(f64, f64)>]) -> String {
+    let mut d = String::new();
+
+    for poly in polys {
+        for (i, (x, y)) in poly.iter().enumerate() {
+            let cmd = if i == 0 { 'M' } else { 'L' };
+            write!(d, "{cmd}{x:.2} {y:.2} ").unwrap();
+        }
+        d.push('Z');
+    }
+
+    d
+}
+
+// overlay lines keep a 1px width however the svg is scaled
+pub(crate) fn write_overlay(out: &mut String, shapes: &[OverlayShape], indent: &str) {
+    for shape in shapes {
+        match *shape {
+            OverlayShape::Polyline { ref points, closed, color: [r, g, b] } => {
+                let tag = if closed { "polygon" } else { "polyline" };
+                let points: Vec<String> = points.iter().map(|(x, y)| { format!("{x:.2},{y:.2}") }).collect();
+                writeln!(
+                    out,
+                    "{indent}<{tag} points=\"{}\" fill=\"none\" stroke=\"#{r:02x}{g:02x}{b:02x}\" vector-effect=\"non-scaling-stroke\"/>",
+                    points.join(" "),
+                ).unwrap();
+            }
+            OverlayShape::Line { from, to, color: [r, g, b] } => writeln!(
+                out,
+                "{indent}<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" stroke=\"#{r:02x}{g:02x}{b:02x}\" vector-effect=\"non-scaling-stroke\"/>",
+                from.0, from.1, to.0, to.1,
+            ).unwrap(),
+            OverlayShape::Circle { center, radius, color: [r, g, b] } => writeln!(
+                out,
+                "{indent}<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{radius}\" fill=\"none\" stroke=\"#{r:02x}{g:02x}{b:02x}\" vector-effect=\"non-scaling-stroke\"/>",
+                center.0, center.1,
+            ).unwrap(),
+        }
+    }
+}
+
+impl ClipFile {
+    fn write_svg_layer(&self, out: &mut String, layer: &Layer, depth: usize) {
+        let indent = "  ".repeat(depth);
+
+        write!(out, "{indent}<g id=\"layer-{}\" data-name=\"{}\"", layer.id, escape(&layer.name)).unwrap();
+        if layer.alpha < 256 {
+            write!(out, " opacity=\"{:.3}\"", layer.alpha as f64 / 256.0).unwrap();
+        }
+        if !layer.is_visible() {
+            out.push_str(" display=\"none\"");
+        }
+        out.push_str(">\n");
+
+        for stroke in self.vector_strokes(layer.id) {
+            let [r, g, b] = stroke.main_color;
+
+            let opacity = stroke_opacity(&stroke);
+
+            write!(out, "{indent}  <path d=\"{}\" fill=\"#{r:02x}{g:02x}{b:02x}\"", path_data(&stroke_outline(&stroke))).unwrap();
+            if opacity < 1.0 {
+                write!(out, " fill-opacity=\"{opacity:.3}\"").unwrap();
+            }
+            out.push_str("/>\n");
+        }
+
+        if layer.is_folder() {
+            self.write_svg_children(out, layer, depth + 1);
+        }
+
+        writeln!(out, "{indent}</g>").unwrap();
+    }
+
+    fn write_svg_children(&self, out: &mut String, folder: &Layer, depth: usize) {
+        let db = self.db();
+        let children = walk_list(folder.first_child_id, |id| {
+            db.get_layer(id).map(|layer| { let next_id = layer.next_id; (layer, next_id) })
+        });
+
+        for layer in &children {
+            self.write_svg_layer(out, layer, depth);
+        }
+    }
+
+    /// the vector layers of the canvas as an svg document, with a group for every layer
+    /// strokes are filled outlines so their width can vary with pressure
+    pub fn export_vector_svg(&self, canvas_id: i64) -> Option<String> {
+        let canvas = self.db().get_canvas(canvas_id)?;
+        let root = self.db().get_layer(canvas.root_folder_id)?;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = canvas.width,
+            h = canvas.height,
+        ).unwrap();
+
+        self.write_svg_children(&mut out, &root, 1);
+        out.push_str("</svg>\n");
+
+        Some(out)
+    }
+
+    /// the canvas' perspective rulers as an svg the size of the canvas, to be laid over an export
+    pub fn export_perspective_svg(&self, canvas_id: i64) -> Option<String> {
+        let canvas = self.db().get_canvas(canvas_id)?;
+        let shapes = self.perspective_overlay(canvas_id)?;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+            w = canvas.width,
+            h = canvas.height,
+        ).unwrap();
+
+        out.push_str("  <g id=\"perspective-rulers\" opacity=\"0.75\">\n");
+        write_overlay(&mut out, &shapes, "    ");
+        out.push_str("  </g>\n</svg>\n");
+
+        Some(out)
+    }
+
+    /// every special ruler of the canvas as an svg overlay, with a group per ruler kind
+    pub fn export_rulers_svg(&self, canvas_id: i64) -> Option<String> {
+        let canvas = self.db().get_canvas(canvas_id)?;
+        let (width, height) = (canvas.width, canvas.height);
+        let rulers = self.special_rulers(canvas_id);
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">",
+        ).unwrap();
+
+        for ruler in &rulers {
+            writeln!(
+                out,
+                "  <g id=\"ruler-{}-{}\" data-layer=\"{}\" opacity=\"0.75\">",
+                ruler.kind.name(), ruler.id, ruler.layer_id,
+            ).unwrap();
+            write_overlay(&mut out, &ruler_shapes(std::slice::from_ref(ruler), width, height), "    ");
+            out.push_str("  </g>\n");
+        }
+        out.push_str("</svg>\n");
+
+        Some(out)
+    }
+}
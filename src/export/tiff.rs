@@ -0,0 +1,140 @@
+use crate::{ClipFile, CmykImage, ImageMetadata, RgbaImage};
+
+// field types
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+const UNDEFINED: u16 = 7;
+
+enum Photometric {
+    Rgb = 2,
+    Separated = 5, // cmyk
+}
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    data: Vec<u8>, // little endian, stored inline if it fits in 4 bytes
+}
+
+fn short(tag: u16, values: &[u16]) -> Entry {
+    Entry { tag, kind: SHORT, count: values.len() as u32, data: values.iter().flat_map(|v| { v.to_le_bytes() }).collect() }
+}
+
+fn long(tag: u16, value: u32) -> Entry {
+    Entry { tag, kind: LONG, count: 1, data: value.to_le_bytes().to_vec() }
+}
+
+fn rational(tag: u16, value: f64) -> Entry {
+    let (num, den) = ((value * 1000.0).round() as u32, 1000u32);
+    Entry { tag, kind: RATIONAL, count: 1, data: [num.to_le_bytes(), den.to_le_bytes()].concat() }
+}
+
+// uncompressed single strip tiff, alpha is written as an unassociated extra sample
+// None if an offset doesn't fit in the u32 classic tiff uses (past 4 GiB)
+fn encode_tiff(width: u32, height: u32, channels: u16, photometric: Photometric, alpha: bool, pixels: &[u8], metadata: &ImageMetadata) -> Option<Vec<u8>> {
+    const HEADER_SIZE: u32 = 8;
+    let strip_size = u32::try_from(pixels.len()).ok()?;
+
+    let mut entries = vec![
+        long(256, width),
+        long(257, height),
+        short(258, &vec![8; channels as usize]),
+        short(259, &[1]), // no compression
+        short(262, &[photometric as u16]),
+        long(273, 0), // strip offset, filled in below
+        short(277, &[channels]),
+        long(278, height),
+        long(279, strip_size),
+        rational(282, metadata.dpi.unwrap_or(72.0)),
+        rational(283, metadata.dpi.unwrap_or(72.0)),
+        short(284, &[1]), // chunky
+        short(296, &[2]), // inches
+    ];
+
+    if channels == 4 && !alpha {
+        entries.push(short(332, &[1])); // ink set: cmyk
+    }
+    if alpha {
+        entries.push(short(338, &[2]));
+    }
+    if let Some(icc) = &metadata.icc_profile {
+        entries.push(Entry { tag: 34675, kind: UNDEFINED, count: u32::try_from(icc.len()).ok()?, data: icc.clone() });
+    }
+
+    entries.sort_by_key(|e| { e.tag });
+
+    // header, pixels, ifd, then any values too big to be inline
+    let ifd_offset = HEADER_SIZE.checked_add(strip_size)?.checked_add(strip_size & 1)?;
+    let ifd_size = 2 + entries.len() as u32 * 12 + 4;
+
+    let mut out = vec![];
+    out.extend(b"II*\0");
+    out.extend(ifd_offset.to_le_bytes());
+    out.extend(pixels);
+    out.resize(ifd_offset as usize, 0);
+
+    let mut extra: Vec<u8> = vec![];
+    out.extend((entries.len() as u16).to_le_bytes());
+
+    for e in &entries {
+        out.extend(e.tag.to_le_bytes());
+        out.extend(e.kind.to_le_bytes());
+        out.extend(e.count.to_le_bytes());
+
+        if e.tag == 273 {
+            out.extend(HEADER_SIZE.to_le_bytes());
+        } else if e.data.len() <= 4 {
+            let mut inline = e.data.clone();
+            inline.resize(4, 0);
+            out.extend(inline);
+        } else {
+            let offset = ifd_offset.checked_add(ifd_size)?.checked_add(u32::try_from(extra.len()).ok()?)?;
+            out.extend(offset.to_le_bytes());
+            extra.extend(&e.data);
+            extra.resize(extra.len() + (extra.len() & 1), 0); // word aligned
+        }
+    }
+
+    out.extend(0u32.to_le_bytes()); // no next ifd
+    out.extend(extra);
+
+    Some(out)
+}
+
+impl RgbaImage {
+    /// uncompressed rgba tiff, None if it's too big for a (non BigTIFF) tiff
+    pub fn to_tiff_with(&self, metadata: &ImageMetadata) -> Option<Vec<u8>> {
+        encode_tiff(self.width, self.height, 4, Photometric::Rgb, true, &self.data, metadata)
+    }
+}
+
+impl CmykImage {
+    /// uncompressed cmyk tiff, the icc profile should be the one the image was converted to
+    /// None if it's too big for a (non BigTIFF) tiff
+    pub fn to_tiff_with(&self, metadata: &ImageMetadata) -> Option<Vec<u8>> {
+        encode_tiff(self.width, self.height, 4, Photometric::Separated, false, &self.data, metadata)
+    }
+}
+
+impl ClipFile {
+    /// the flattened canvas as an rgba tiff, tagged with the canvas' resolution and colour profile if it has one
+    /// None if the render is bigger than a tiff can hold (4 GiB)
+    pub fn export_canvas_tiff(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let img = self.render_canvas(canvas_id, scale)?;
+        img.to_tiff_with(&self.image_metadata(canvas_id, scale))
+    }
+
+    /// the canvas converted with its simulation profile as a cmyk tiff (with the profile and resolution embedded)
+    pub fn export_cmyk_tiff(&self, canvas_id: i64, scale: f64) -> Option<Vec<u8>> {
+        let img = self.render_cmyk(canvas_id, scale)?;
+        let colors = self.db().get_canvas_color_settings(canvas_id)?;
+
+        let metadata = ImageMetadata {
+            icc_profile: colors.simulate_dst_profile.map(|p| { p.data }),
+            ..self.image_metadata(canvas_id, scale)
+        };
+        img.to_tiff_with(&metadata)
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::Write;
+
+use crate::{CelExposure, ClipFile};
+use crate::export::json;
+
+/// the cels of one animation folder over the timeline
+#[derive(Debug, Clone)]
+pub struct XSheetColumn {
+    pub folder_id: i64,
+    pub name: String,
+    pub exposures: Vec<CelExposure>,
+}
+
+impl XSheetColumn {
+    /// the exposure covering the given frame, if any cel is shown
+    pub fn exposure_at(&self, frame: i64) -> Option<&CelExposure> {
+        self.exposures.iter().rev().find(|e| { e.frame <= frame }).filter(|e| { frame < e.frame + e.length })
+    }
+}
+
+/// the timing of a timeline as far as the track labels tell, one column per animation folder
+/// see `Track::label_exposures`, the fallback for the cel specifications in the track data, which aren't decoded
+#[derive(Debug, Clone)]
+pub struct ExposureSheet {
+    pub timeline: String,
+    pub frame_rate: f64,
+    pub start_frame: i64,
+    pub end_frame: i64,
+    pub columns: Vec<XSheetColumn>,
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+impl ExposureSheet {
+    /// one row per frame and folder: `frame,folder,cel,start,length`
+    /// start and length are those of the exposure the frame is part of, frames without a cel are left empty
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("frame,folder,cel,start,length\n");
+
+        for frame in self.start_frame..=self.end_frame {
+            for col in &self.columns {
+                write!(out, "{frame},{},", csv_field(&col.name)).unwrap();
+
+                match col.exposure_at(frame) {
+                    Some(e) => writeln!(out, "{},{},{}", csv_field(&e.cel), e.frame, e.length).unwrap(),
+                    None => out.push_str(",,\n"),
+                }
+            }
+        }
+
+        out
+    }
+
+    /// the exposures of each folder followed by the cel of every folder per frame
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+
+        writeln!(out, "  \"timeline\": {},", json::string(&self.timeline)).unwrap();
+        writeln!(out, "  \"frame_rate\": {},", json::number(self.frame_rate)).unwrap();
+        writeln!(out, "  \"start_frame\": {},", self.start_frame).unwrap();
+        writeln!(out, "  \"end_frame\": {},", self.end_frame).unwrap();
+        writeln!(out, "  \"exposures_from\": \"track_labels\",").unwrap();
+
+        let folders: Vec<String> = self.columns.iter().map(|col| {
+            let exposures: Vec<String> = col.exposures.iter().map(|e| {
+                format!("{{\"frame\": {}, \"length\": {}, \"cel\": {}}}", e.frame, e.length, json::string(&e.cel))
+            }).collect();
+
+            format!(
+                "    {{\"id\": {}, \"name\": {}, \"exposures\": [{}]}}",
+                col.folder_id, json::string(&col.name), exposures.join(", ")
+            )
+        }).collect();
+        writeln!(out, "  \"folders\": [\n{}\n  ],", folders.join(",\n")).unwrap();
+
+        let frames: Vec<String> = (self.start_frame..=self.end_frame).map(|frame| {
+            let cels: Vec<String> = self.columns.iter().map(|col| {
+                let cel = col.exposure_at(frame).map_or("null".to_string(), |e| { json::string(&e.cel) });
+                format!("{}: {cel}", json::string(&col.name))
+            }).collect();
+
+            format!("    {{\"frame\": {frame}, \"cels\": {{{}}}}}", cels.join(", "))
+        }).collect();
+        writeln!(out, "  \"frames\": [\n{}\n  ]", frames.join(",\n")).unwrap();
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl ClipFile {
+    /// the exposure sheet of the current timeline from its track labels, folders without a track are left out
+    pub fn exposure_sheet(&self, canvas_id: i64) -> Option<ExposureSheet> {
+        let db = self.db();
+        let timeline = db.get_current_timeline()?;
+        let (start_frame, end_frame) = (*timeline.frames().start(), *timeline.frames().end());
+
+        let columns = db.get_animation_folders(canvas_id).into_iter().filter_map(|id| {
+            let folder = db.get_layer(id)?;
+            let track = timeline.track_for_layer(folder.uuid.as_deref()?)?;
+
+            Some(XSheetColumn { folder_id: id, name: folder.name, exposures: track.label_exposures(end_frame) })
+        }).collect();
+
+        Some(ExposureSheet {
+            timeline: timeline.name,
+            frame_rate: timeline.frame_rate,
+            start_frame,
+            end_frame,
+            columns,
+        })
+    }
+}
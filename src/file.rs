@@ -0,0 +1,77 @@
+use std::io;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::{ClipDb, ClipExtaHeader, ClipHeader, ClipSqliteChunk, Exta3DData, ExtaOffscreen, ExtaVector, Track, VectorStroke};
+use crate::chunks::sqli::SchemaCache;
+use crate::dbutil::copied_connection;
+
+/// an entire clip file loaded into memory
+pub struct ClipFile {
+    data: Vec<u8>,
+    header: ClipHeader,
+    conn: Connection,
+    schema: SchemaCache,
+}
+
+impl ClipFile {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> io::Result<Self> {
+        let invalid = |msg: &str| { io::Error::new(io::ErrorKind::InvalidData, msg) };
+
+        let (_, header) = ClipHeader::parse(&data).map_err(|_| { invalid("Invalid clip header") })?;
+
+        let sql_chunk = data.get(header.sqlite_chunk_pos as usize..).ok_or_else(|| { invalid("Invalid SQLite chunk position") })?;
+        let (_, sqlite_data) = ClipSqliteChunk::extract_data(sql_chunk).map_err(|_| { invalid("Failed to parse SQLite chunk") })?;
+
+        let conn = copied_connection(sqlite_data).map_err(|_| { invalid("Unable to open sqlite") })?;
+
+        Ok(Self { data, header, conn, schema: SchemaCache::default() })
+    }
+
+    pub fn header(&self) -> &ClipHeader {
+        &self.header
+    }
+
+    pub fn db(&self) -> ClipDb<'_> {
+        ClipDb::with_cache(&self.conn, &self.schema)
+    }
+
+    /// body of the external chunk at the given offset
+    pub fn exta_body(&self, offset: i64) -> Option<&[u8]> {
+        let chunk = self.data.get(offset as usize..)?;
+        let (body, exta) = ClipExtaHeader::parse(chunk).ok()?;
+
+        body.get(..exta.body_size as usize)
+    }
+
+    /// the block data for the offscreen with the given ID
+    pub fn offscreen(&self, offscreen_id: i64) -> Option<ExtaOffscreen<'_>> {
+        let offset = self.db().get_offscreen_exta_offset(offscreen_id)?;
+        ExtaOffscreen::parse(self.exta_body(offset)?).ok().map(|(_, o)| { o })
+    }
+
+    /// all vector strokes of the given layer, empty for non vector layers
+    pub fn vector_strokes(&self, layer_id: i64) -> Vec<VectorStroke> {
+        self.db().get_vector_exta_offsets(layer_id).into_iter()
+            .filter_map(|offset| { self.exta_body(offset) })
+            .filter_map(|body| { ExtaVector::parse(body).ok() })
+            .flat_map(|(_, v)| { v.strokes })
+            .collect()
+    }
+
+    /// the header of a 3d layer's scene data, the payload is left as it is
+    pub fn scene_data_3d(&self, layer_id: i64) -> Option<Exta3DData<'_>> {
+        let offset = self.db().get_scene_3d(layer_id)?.scene_exta_offset?;
+        Exta3DData::parse(self.exta_body(offset)?).ok().map(|(_, d)| { d })
+    }
+
+    /// the raw TrackActionMixer data of an animation track, the format is unknown
+    pub fn track_data(&self, track: &Track) -> Option<&[u8]> {
+        self.exta_body(track.action_mixer_exta_offset?)
+    }
+}
@@ -1,10 +1,41 @@
+pub use chunks::exta::data3d::Exta3DData;
 pub use chunks::exta::offscreen::{BlockData, BlockDataChunk, ExtaOffscreen};
-pub use chunks::exta::ClipExtaHeader;
+pub use chunks::exta::{ClipExtaBody, ClipExtaHeader};
+pub use chunks::exta::vector::{ExtaVector, VectorPoint, VectorStroke};
 pub use chunks::head::ClipHeader;
-pub use chunks::sqli::{ClipDb, ClipSqliteChunk};
+pub use chunks::sqli::{ClipDb, ClipSqliteChunk, SchemaCache};
+pub use chunks::sqli::brush::{
+    BrushColorJitter, BrushEffector, BrushParam, BrushPatternImage, BrushPatternStyle, BrushSpray, BrushStyle, BrushStyleManager,
+    BrushTexture, BrushWaterEdge, BrushWatercolor, EffectorInput, FillStyle,
+};
+pub use chunks::sqli::canvas::{Canvas, CanvasColorSettings, CanvasPreview, CanvasUnit, IccProfile, PreviewFormat, RenderingIntent};
+pub use chunks::sqli::layer::{FilterLayerInfo, Layer, LayerBlendMode, LayerEffect, LayerKind};
+pub use chunks::sqli::mipmap::{MipmapChain, MipmapLevel};
+pub use chunks::sqli::offscreen::OffscreenAttribute;
+pub use chunks::sqli::project::{DefaultPage, Project};
+pub use chunks::sqli::ruler::{Guide, PerspectiveRuler, SpecialRuler, SpecialRulerKind, VanishingPoint};
+pub use chunks::sqli::scene3d::{Camera, Frustum, Layer3D, LayerObject, LayerObjectKind, Light, LightKind, Scene3D};
+pub use chunks::sqli::schema::{Capability, SchemaInfo};
+pub use chunks::sqli::scheme::{DataType, ElemScheme, ParamScheme, Row, Value};
+pub use chunks::sqli::thumbnail::{LayerThumbnail, ThumbnailSize};
+pub use chunks::sqli::timelapse::{TimeLapseBlob, TimeLapseRecord};
+pub use chunks::sqli::timeline::{AnimationCutBank, CelExposure, Timeline, TimelineLabel, Track};
+pub use color::CmykImage;
+pub use file::ClipFile;
+pub use export::animation::AnimationFrame;
+pub use export::patterns::PatternImage;
+pub use export::png::ImageMetadata;
+pub use export::xsheet::{ExposureSheet, XSheetColumn};
+pub use render::RgbaImage;
+pub use render::thumbnail::LayerThumbnailImage;
 
 mod dbutil;
 mod chunks;
+mod color;
+mod export;
+mod file;
+mod render;
+mod vector;
 
 pub mod util {
     use std::fs::File;
@@ -69,15 +100,15 @@ pub mod util {
                     let (_, block): (&[u8], ExtaOffscreen) = ExtaOffscreen::parse(exta_buf.as_slice()).expect("Failed block parse");
 
                     for (i, chunk) in block.chunks.iter().enumerate() {
-                        let mut data = chunk.decompress();
+                        let Some(data) = chunk.decompress() else { continue };
                         
                         // dir
                         let dir = out_dir.join(format!("layer-id_{id}/chunk-offset_{offset}"));
                         std::fs::create_dir_all(&dir).unwrap();
                         
                         // blocks
-                        let out = File::create_new(&dir.join(format!("block_{i:0>5}")));
-                        out.unwrap().write_all(&mut data).unwrap();
+                        let out = File::create_new(dir.join(format!("block_{i:0>5}")));
+                        out.unwrap().write_all(&data).unwrap();
                     }
                 }
             }
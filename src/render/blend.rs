@@ -0,0 +1,228 @@
+use crate::render::RgbaImage;
+use crate::LayerBlendMode;
+
+// https://www.w3.org/TR/compositing-1/#blending
+// csp's own modes (glow dodge, add glow) are approximated with their regular versions
+
+fn multiply(cb: f32, cs: f32) -> f32 {
+    cb * cs
+}
+
+fn screen(cb: f32, cs: f32) -> f32 {
+    cb + cs - cb * cs
+}
+
+fn color_dodge(cb: f32, cs: f32) -> f32 {
+    if cb <= 0.0 {
+        0.0
+    } else if cs >= 1.0 {
+        1.0
+    } else {
+        (cb / (1.0 - cs)).min(1.0)
+    }
+}
+
+fn color_burn(cb: f32, cs: f32) -> f32 {
+    if cb >= 1.0 {
+        1.0
+    } else if cs <= 0.0 {
+        0.0
+    } else {
+        1.0 - ((1.0 - cb) / cs).min(1.0)
+    }
+}
+
+fn hard_light(cb: f32, cs: f32) -> f32 {
+    if cs <= 0.5 {
+        multiply(cb, 2.0 * cs)
+    } else {
+        screen(cb, 2.0 * cs - 1.0)
+    }
+}
+
+fn soft_light(cb: f32, cs: f32) -> f32 {
+    if cs <= 0.5 {
+        cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+    } else {
+        let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+        cb + (2.0 * cs - 1.0) * (d - cb)
+    }
+}
+
+fn vivid_light(cb: f32, cs: f32) -> f32 {
+    if cs <= 0.5 {
+        color_burn(cb, 2.0 * cs)
+    } else {
+        color_dodge(cb, 2.0 * cs - 1.0)
+    }
+}
+
+fn pin_light(cb: f32, cs: f32) -> f32 {
+    if cs <= 0.5 {
+        cb.min(2.0 * cs)
+    } else {
+        cb.max(2.0 * cs - 1.0)
+    }
+}
+
+fn separable(mode: LayerBlendMode, cb: f32, cs: f32) -> f32 {
+    match mode {
+        LayerBlendMode::Darken => cb.min(cs),
+        LayerBlendMode::Multiply => multiply(cb, cs),
+        LayerBlendMode::ColorBurn => color_burn(cb, cs),
+        LayerBlendMode::LinearBurn => (cb + cs - 1.0).max(0.0),
+        LayerBlendMode::Subtract => (cb - cs).max(0.0),
+        LayerBlendMode::Lighten => cb.max(cs),
+        LayerBlendMode::Screen => screen(cb, cs),
+        LayerBlendMode::ColorDodge | LayerBlendMode::GlowDodge => color_dodge(cb, cs),
+        LayerBlendMode::Add | LayerBlendMode::AddGlow => (cb + cs).min(1.0),
+        LayerBlendMode::Overlay => hard_light(cs, cb),
+        LayerBlendMode::SoftLight => soft_light(cb, cs),
+        LayerBlendMode::HardLight => hard_light(cb, cs),
+        LayerBlendMode::VividLight => vivid_light(cb, cs),
+        LayerBlendMode::LinearLight => (cb + 2.0 * cs - 1.0).clamp(0.0, 1.0),
+        LayerBlendMode::PinLight => pin_light(cb, cs),
+        LayerBlendMode::HardMix => if cb + cs >= 1.0 { 1.0 } else { 0.0 },
+        LayerBlendMode::Difference => (cb - cs).abs(),
+        LayerBlendMode::Exclusion => cb + cs - 2.0 * cb * cs,
+        LayerBlendMode::Divide => if cs <= 0.0 { 1.0 } else { (cb / cs).min(1.0) },
+        _ => cs,
+    }
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+
+    c.map(|v| {
+        let mut v = v;
+        if n < 0.0 { v = l + (v - l) * l / (l - n) }
+        if x > 1.0 { v = l + (v - l) * (1.0 - l) / (x - l) }
+        v
+    })
+}
+
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    clip_color(c.map(|v| { v + d }))
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+
+    if x <= n {
+        return [0.0; 3];
+    }
+
+    c.map(|v| { (v - n) * s / (x - n) })
+}
+
+/// blends a single (straight) rgb colour
+pub(crate) fn blend(mode: LayerBlendMode, cb: [f32; 3], cs: [f32; 3]) -> [f32; 3] {
+    match mode {
+        LayerBlendMode::Hue => set_lum(set_sat(cs, sat(cb)), lum(cb)),
+        LayerBlendMode::Saturation => set_lum(set_sat(cb, sat(cs)), lum(cb)),
+        LayerBlendMode::Color => set_lum(cs, lum(cb)),
+        LayerBlendMode::Brightness => set_lum(cb, lum(cs)),
+        LayerBlendMode::DarkerColor => if cs.iter().sum::<f32>() < cb.iter().sum() { cs } else { cb },
+        LayerBlendMode::LighterColor => if cs.iter().sum::<f32>() > cb.iter().sum() { cs } else { cb },
+        _ => [0, 1, 2].map(|i| { separable(mode, cb[i], cs[i]) }),
+    }
+}
+
+/// composites `src` on top of `dst` (both the same size)
+pub(crate) fn composite(dst: &mut RgbaImage, src: &RgbaImage, opacity: f32, mode: LayerBlendMode) {
+    for (d, s) in dst.data.chunks_exact_mut(4).zip(src.data.chunks_exact(4)) {
+        let a_s = s[3] as f32 / 255.0 * opacity;
+        if a_s <= 0.0 {
+            continue;
+        }
+
+        let a_b = d[3] as f32 / 255.0;
+        let cb = [d[0], d[1], d[2]].map(|v| { v as f32 / 255.0 });
+        let cs = [s[0], s[1], s[2]].map(|v| { v as f32 / 255.0 });
+
+        let b = blend(mode, cb, cs);
+        let a_o = a_s + a_b * (1.0 - a_s);
+
+        for i in 0..3 {
+            let mixed = (1.0 - a_b) * cs[i] + a_b * b[i];
+            let c = (a_s * mixed + a_b * cb[i] * (1.0 - a_s)) / a_o;
+            d[i] = (c * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+
+        d[3] = (a_o * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(c: [u8; 4]) -> RgbaImage {
+        let mut img = RgbaImage::new(1, 1);
+        img.put_pixel(0, 0, c);
+        img
+    }
+
+    #[test]
+    fn separable_modes() {
+        let (cb, cs) = ([0.5, 0.2, 1.0], [0.5, 0.8, 0.0]);
+
+        assert_eq!(blend(LayerBlendMode::Normal, cb, cs), cs);
+        assert_eq!(blend(LayerBlendMode::Multiply, cb, cs), [0.25, 0.16000001, 0.0]);
+        assert_eq!(blend(LayerBlendMode::Screen, cb, cs), [0.75, 0.84, 1.0]);
+        assert_eq!(blend(LayerBlendMode::Darken, cb, cs), [0.5, 0.2, 0.0]);
+        assert_eq!(blend(LayerBlendMode::Lighten, cb, cs), [0.5, 0.8, 1.0]);
+        assert_eq!(blend(LayerBlendMode::Add, cb, cs), [1.0, 1.0, 1.0]);
+        assert_eq!(blend(LayerBlendMode::Subtract, cb, cs), [0.0, 0.0, 1.0]);
+        assert_eq!(blend(LayerBlendMode::HardMix, cb, cs), [1.0, 1.0, 1.0]);
+        assert_eq!(blend(LayerBlendMode::Unknown(99), cb, cs), cs);
+    }
+
+    #[test]
+    fn non_separable_modes() {
+        let gray = [0.5, 0.5, 0.5];
+        let red = [1.0, 0.0, 0.0];
+
+        // colour keeps the base luminosity
+        let c = blend(LayerBlendMode::Color, gray, red);
+        assert!((lum(c) - 0.5).abs() < 1e-5);
+        assert!(c[0] > c[1] && c[1] == c[2]);
+
+        // a gray base has no saturation to give
+        assert_eq!(blend(LayerBlendMode::Hue, gray, red), gray);
+        assert_eq!(blend(LayerBlendMode::DarkerColor, gray, red), red);
+        assert_eq!(blend(LayerBlendMode::LighterColor, gray, red), gray);
+    }
+
+    #[test]
+    fn composite_alpha() {
+        let mut dst = pixel([0, 0, 255, 255]);
+        composite(&mut dst, &pixel([255, 0, 0, 255]), 1.0, LayerBlendMode::Normal);
+        assert_eq!(dst.data, [255, 0, 0, 255]);
+
+        let mut dst = pixel([0, 0, 255, 255]);
+        composite(&mut dst, &pixel([255, 0, 0, 255]), 0.5, LayerBlendMode::Normal);
+        assert_eq!(dst.data, [128, 0, 128, 255]);
+
+        // blending only applies where there's something below
+        let mut dst = pixel([0, 0, 0, 0]);
+        composite(&mut dst, &pixel([255, 255, 255, 255]), 1.0, LayerBlendMode::Multiply);
+        assert_eq!(dst.data, [255, 255, 255, 255]);
+
+        let mut dst = pixel([10, 20, 30, 255]);
+        composite(&mut dst, &pixel([255, 255, 255, 0]), 1.0, LayerBlendMode::Normal);
+        assert_eq!(dst.data, [10, 20, 30, 255]);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{LayerBlendMode, LayerEffect};
+use crate::render::blend::composite;
+use crate::render::RgbaImage;
+
+/// widest border that gets drawn, in output pixels
+const MAX_BORDER_WIDTH: f64 = 1024.0;
+
+/// draws a border of the given width and colour around the opaque parts of the image
+pub(crate) fn apply_border(img: &mut RgbaImage, width: f64, color: [u8; 3]) {
+    if !width.is_finite() || width <= 0.0 {
+        return;
+    }
+
+    let width = width.min(MAX_BORDER_WIDTH);
+    let (w, h) = (img.width as i64, img.height as i64);
+    let r = width.ceil() as i64;
+
+    // (dx, dy, coverage)
+    let kernel: Vec<(i64, i64, f32)> = (-r..=r)
+        .flat_map(|dy| { (-r..=r).map(move |dx| { (dx, dy) }) })
+        .map(|(dx, dy)| { (dx, dy, ((width + 0.5 - ((dx * dx + dy * dy) as f64).sqrt()) as f32).clamp(0.0, 1.0)) })
+        .filter(|(_, _, c)| { *c > 0.0 })
+        .collect();
+
+    let alpha = |x: i64, y: i64| -> u8 {
+        if x < 0 || y < 0 || x >= w || y >= h { 0 } else { img.data[((y * w + x) * 4 + 3) as usize] }
+    };
+
+    let mut border = RgbaImage::new(img.width, img.height);
+
+    for y in 0..h {
+        for x in 0..w {
+            let a = alpha(x, y);
+            if a == 0 {
+                continue;
+            }
+
+            // the inside is covered by the image itself so only the edges need stamping
+            let is_edge = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| { alpha(x + dx, y + dy) < 255 });
+            if !is_edge {
+                continue;
+            }
+
+            for (dx, dy, c) in &kernel {
+                let (bx, by) = (x + dx, y + dy);
+                if bx < 0 || by < 0 || bx >= w || by >= h {
+                    continue;
+                }
+
+                let p = &mut border.data[((by * w + bx) * 4) as usize..][..4];
+                p[3] = p[3].max((a as f32 * c).round() as u8);
+            }
+        }
+    }
+
+    for p in border.data.chunks_exact_mut(4) {
+        p[..3].copy_from_slice(&color);
+    }
+
+    composite(&mut border, img, 1.0, LayerBlendMode::Normal);
+    *img = border;
+}
+
+/// applies the effects that are currently supported to the rendered layer
+/// sizes are scaled along with the render
+pub(crate) fn apply_effects(img: &mut RgbaImage, effects: &[LayerEffect], scale: f64) {
+    for effect in effects {
+        match effect {
+            LayerEffect::Border { enabled: true, width, color } if *width > 0.0 => apply_border(img, *width * scale, *color),
+            _ => {} // todo: tone, watercolour edge, line extraction
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dot() -> RgbaImage {
+        let mut img = RgbaImage::new(9, 9);
+        img.put_pixel(4, 4, [0, 0, 0, 255]);
+        img
+    }
+
+    #[test]
+    fn border_around_opaque_pixels() {
+        let mut img = dot();
+        apply_border(&mut img, 2.0, [255, 0, 0]);
+
+        assert_eq!(img.pixel(4, 4), [0, 0, 0, 255]);
+        assert_eq!(img.pixel(5, 4), [255, 0, 0, 255]);
+        assert_eq!(img.pixel(6, 4), [255, 0, 0, 128]); // antialiased edge
+        assert_eq!(img.pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn unusable_border_widths() {
+        for width in [f64::NAN, f64::INFINITY, -1.0, 0.0] {
+            let mut img = dot();
+            apply_border(&mut img, width, [255, 0, 0]);
+            assert_eq!(img, dot());
+        }
+
+        // clamped instead of building a kernel the size of the width
+        let mut img = dot();
+        apply_border(&mut img, 1e12, [255, 0, 0]);
+        assert_eq!(img.pixel(0, 0), [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{BrushPatternImage, ClipFile, ExtaOffscreen, Layer, LayerBlendMode, LayerKind, MipmapChain, OffscreenAttribute, Timeline};
+use crate::chunks::sqli::walk_list;
+use crate::render::blend::composite;
+use crate::render::effect::apply_effects;
+use crate::render::raster::draw_stroke;
+use crate::vector::scale_stroke;
+
+mod blend;
+mod effect;
+pub(crate) mod overlay;
+mod png;
+mod raster;
+pub(crate) mod thumbnail;
+
+/// 8 bit rgba image with straight (non premultiplied) alpha
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// a fully transparent image
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, data: vec![0u8; width as usize * height as usize * 4] }
+    }
+
+    pub fn filled(width: u32, height: u32, color: [u8; 4]) -> Self {
+        Self { width, height, data: color.repeat(width as usize * height as usize) }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]]
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: [u8; 4]) {
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        self.data[i..i + 4].copy_from_slice(&color);
+    }
+
+    /// copies `src` onto this image with its top left corner at (x, y), clipping anything outside
+    pub fn paste(&mut self, src: &RgbaImage, x: i64, y: i64) {
+        for sy in 0..src.height as i64 {
+            let dy = y + sy;
+            if dy < 0 || dy >= self.height as i64 {
+                continue;
+            }
+
+            let sx0 = (-x).clamp(0, src.width as i64);
+            let sx1 = (self.width as i64 - x).clamp(0, src.width as i64);
+            if sx0 >= sx1 {
+                continue;
+            }
+
+            let s = (sy * src.width as i64 + sx0) as usize * 4;
+            let d = (dy * self.width as i64 + x + sx0) as usize * 4;
+            let n = (sx1 - sx0) as usize * 4;
+
+            self.data[d..d + n].copy_from_slice(&src.data[s..s + n]);
+        }
+    }
+
+    /// resamples the image to the given size, averaging (alpha weighted) when shrinking
+    pub fn resized(&self, width: u32, height: u32) -> RgbaImage {
+        let mut out = RgbaImage::new(width, height);
+        if self.width == 0 || self.height == 0 {
+            return out;
+        }
+
+        let (sx, sy) = (self.width as f64 / width as f64, self.height as f64 / height as f64);
+
+        // source range covered by a destination pixel, at least one pixel wide
+        let range = |d: u32, s: f64, max: u32| -> (u32, u32) {
+            let start = ((d as f64 * s) as u32).min(max - 1);
+            let end = (((d + 1) as f64 * s).ceil() as u32).clamp(start + 1, max);
+            (start, end)
+        };
+
+        for y in 0..height {
+            let (y0, y1) = range(y, sy, self.height);
+
+            for x in 0..width {
+                let (x0, x1) = range(x, sx, self.width);
+
+                let mut sum = [0u64; 4];
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        let [r, g, b, a] = self.pixel(px, py);
+                        let a = a as u64;
+                        sum[0] += r as u64 * a;
+                        sum[1] += g as u64 * a;
+                        sum[2] += b as u64 * a;
+                        sum[3] += a;
+                    }
+                }
+
+                let n = ((x1 - x0) * (y1 - y0)) as u64;
+                if sum[3] == 0 {
+                    continue;
+                }
+
+                let c = |v: u64| { ((v + sum[3] / 2) / sum[3]) as u8 };
+                out.put_pixel(x, y, [c(sum[0]), c(sum[1]), c(sum[2]), ((sum[3] + n / 2) / n) as u8]);
+            }
+        }
+
+        out
+    }
+
+    /// multiplies the alpha of this image with the alpha of the given mask
+    pub fn mask_with(&mut self, mask: &RgbaImage) {
+        for (p, m) in self.data.chunks_exact_mut(4).zip(mask.data.chunks_exact(4)) {
+            p[3] = ((p[3] as u16 * m[3] as u16 + 127) / 255) as u8;
+        }
+    }
+
+    /// single channel version of the image if it has no colour: the alpha of white images
+    /// (masks decode as white) or the grey of opaque ones
+    pub fn to_gray(&self) -> Option<Vec<u8>> {
+        let pixels = || { self.data.chunks_exact(4) };
+
+        if pixels().all(|p| { p[..3] == [255, 255, 255] }) {
+            return Some(pixels().map(|p| { p[3] }).collect());
+        }
+
+        pixels().all(|p| { p[0] == p[1] && p[1] == p[2] && p[3] == 255 }).then(|| { pixels().map(|p| { p[0] }).collect() })
+    }
+}
+
+// what's being rendered, passed down through nested folders
+#[derive(Copy, Clone)]
+struct RenderTarget<'t> {
+    width: u32,
+    height: u32,
+    scale: f64,
+    frame: Option<(&'t Timeline, i64)>, // animation folders show the cel of this frame
+}
+
+impl<'t> RenderTarget<'t> {
+    fn new(width: u32, height: u32, scale: f64) -> Self {
+        Self { width, height, scale, frame: None }
+    }
+}
+
+/// decodes offscreen block data into an image
+/// colour blocks are an alpha plane followed by a BGRA plane, masks are only the alpha plane (white in the output)
+pub(crate) fn decode_offscreen(attr: &OffscreenAttribute, offscreen: &ExtaOffscreen) -> RgbaImage {
+    let mut img = RgbaImage::new(attr.width, attr.height);
+
+    for chunk in &offscreen.chunks {
+        let (bw, bh) = (chunk.width as usize, chunk.height as usize);
+        let bx = (chunk.index % attr.block_columns.max(1)) as usize * bw;
+        let by = (chunk.index / attr.block_columns.max(1)) as usize * bh;
+
+        let is_mask = chunk.decompressed_size as usize == bw * bh;
+        let data = match chunk.data {
+            Some(_) => match chunk.decompress() {
+                // short blocks are skipped rather than read past their end
+                Some(d) if d.len() >= if is_mask { bw * bh } else { bw * bh * 5 } => Some(d),
+                _ => continue,
+            },
+            None => None,
+        };
+
+        for y in by..(by + bh).min(attr.height as usize) {
+            for x in bx..(bx + bw).min(attr.width as usize) {
+                let i = (y - by) * bw + (x - bx);
+
+                let px = match &data {
+                    Some(d) if is_mask => [255, 255, 255, d[i]],
+                    Some(d) => {
+                        let c = &d[bw * bh + i * 4..][..4];
+                        [c[2], c[1], c[0], d[i]]
+                    }
+                    None if is_mask => [255, 255, 255, attr.init_color.map_or(0, |c| { c[3] })],
+                    None => attr.init_color.unwrap_or([0; 4]),
+                };
+
+                img.put_pixel(x as u32, y as u32, px);
+            }
+        }
+    }
+
+    img
+}
+
+impl ClipFile {
+    /// the decoded offscreen with the given ID
+    pub fn render_offscreen(&self, offscreen_id: i64) -> Option<RgbaImage> {
+        let attr = self.db().get_offscreen_attribute(offscreen_id)?;
+        let offscreen = self.offscreen(offscreen_id)?;
+
+        Some(decode_offscreen(&attr, &offscreen))
+    }
+
+    /// the full size bitmap of a brush tip or texture
+    pub fn render_brush_pattern(&self, pattern: &BrushPatternImage) -> Option<RgbaImage> {
+        let mipmap = self.db().get_mipmap_chain(pattern.mipmap_id)?;
+        self.render_offscreen(mipmap.base()?.offscreen_id)
+    }
+
+    /// decodes the mipmap level closest to the scale and resamples it to exactly that scale
+    fn render_mipmap(&self, mipmap: &MipmapChain, scale: f64) -> Option<RgbaImage> {
+        let level = mipmap.nearest(scale)?;
+        let img = self.render_offscreen(level.offscreen_id)?;
+
+        if (level.scale - scale).abs() < f64::EPSILON {
+            return Some(img);
+        }
+
+        let factor = scale / level.scale;
+        let resize = |v: u32| { ((v as f64 * factor).round() as u32).max(1) };
+
+        Some(img.resized(resize(img.width), resize(img.height)))
+    }
+
+    /// canvas size at the given scale, never less than a pixel on either side
+    fn canvas_size(&self, canvas_id: i64, scale: f64) -> Option<(u32, u32)> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return None;
+        }
+
+        let (width, height) = self.db().get_canvas(canvas_id)?.size_px();
+        let side = |v: u32| { ((v as f64 * scale).round() as u32).max(1) };
+
+        Some((side(width), side(height)))
+    }
+
+    // single layer at the scaled canvas size, before opacity and blending
+    fn render_layer_content(&self, layer: &Layer, target: RenderTarget) -> Option<RgbaImage> {
+        let db = self.db();
+        let strokes = self.vector_strokes(layer.id);
+        let RenderTarget { width, height, scale, .. } = target;
+
+        // not sure if LayerRenderOffscrOffset also applies here, it's always been 0
+        let (offset_x, offset_y) = ((layer.offset_x as f64 * scale).round() as i64, (layer.offset_y as f64 * scale).round() as i64);
+
+        let mut img = match layer.kind {
+            LayerKind::Paper => {
+                let [r, g, b] = db.get_layer_draw_color(layer.id).unwrap_or([255; 3]);
+                RgbaImage::filled(width, height, [r, g, b, 255])
+            }
+            LayerKind::Filter => return None, // todo: filters apply to what's below them
+            _ if layer.is_folder() => self.composite_folder(layer, target),
+            _ if !strokes.is_empty() => RgbaImage::new(width, height),
+            _ => {
+                let offscreen = self.render_mipmap(&db.get_layer_render_mipmap(layer.id)?, scale)?;
+
+                let mut img = RgbaImage::new(width, height);
+                img.paste(&offscreen, offset_x, offset_y);
+                img
+            }
+        };
+
+        // drawn from the strokes rather than the cached offscreen, frame borders go on top of the folder contents
+        for stroke in &strokes {
+            draw_stroke(&mut img, &scale_stroke(stroke, scale));
+        }
+
+        if layer.is_mask_enabled() {
+            if let Some(offscreen) = db.get_layer_mask_mipmap(layer.id).and_then(|m| { self.render_mipmap(&m, scale) }) {
+                let mut mask = RgbaImage::new(width, height);
+                mask.paste(&offscreen, offset_x, offset_y);
+                img.mask_with(&mask);
+            }
+        }
+
+        apply_effects(&mut img, &db.get_layer_effects(layer.id), scale);
+
+        Some(img)
+    }
+
+    // composites all visible children of a folder from the bottom up
+    fn composite_folder(&self, folder: &Layer, target: RenderTarget) -> RgbaImage {
+        let db = self.db();
+
+        // only the cel the track labels name for the frame is shown (regardless of its visibility which is for the current frame)
+        // folders without labels keep their saved visibility since the track data itself isn't decoded
+        let cel = target.frame.filter(|_| { folder.animation_folder }).and_then(|(timeline, frame)| {
+            timeline.label_cel_at(folder.uuid.as_deref()?, frame)
+        });
+
+        let mut out = RgbaImage::new(target.width, target.height);
+        let mut clip_base: Option<RgbaImage> = None;
+
+        let children = walk_list(folder.first_child_id, |id| {
+            db.get_layer(id).map(|layer| { let next_id = layer.next_id; (layer, next_id) })
+        });
+
+        for layer in children {
+            let shown = match &cel {
+                Some(name) => layer.name == *name,
+                None => layer.is_visible(),
+            };
+
+            if !shown {
+                continue;
+            }
+
+            let Some(mut img) = self.render_layer_content(&layer, target) else { continue };
+
+            if layer.clip {
+                if let Some(base) = &clip_base {
+                    img.mask_with(base);
+                }
+            } else {
+                clip_base = Some(img.clone());
+            }
+
+            composite(&mut out, &img, layer.alpha as f32 / 256.0, layer.blend_mode);
+        }
+
+        out
+    }
+
+    /// renders a single layer (including its mask and effects) at the canvas size times `scale`
+    /// the closest stored mipmap level is used so small renders don't decode the full size offscreens
+    /// layer opacity and blend mode are not applied, folders are flattened
+    pub fn render_layer(&self, layer_id: i64, scale: f64) -> Option<RgbaImage> {
+        let layer = self.db().get_layer(layer_id)?;
+        let (width, height) = self.canvas_size(layer.canvas_id, scale)?;
+
+        self.render_layer_content(&layer, RenderTarget::new(width, height, scale))
+    }
+
+    fn render_canvas_target(&self, canvas_id: i64, target: RenderTarget) -> Option<RgbaImage> {
+        let canvas = self.db().get_canvas(canvas_id)?;
+        let root = self.db().get_layer(canvas.root_folder_id)?;
+
+        let mut out = RgbaImage::new(target.width, target.height);
+        composite(&mut out, &self.composite_folder(&root, target), 1.0, LayerBlendMode::Normal);
+
+        Some(out)
+    }
+
+    /// flattens all visible layers of the canvas at the canvas size times `scale`
+    pub fn render_canvas(&self, canvas_id: i64, scale: f64) -> Option<RgbaImage> {
+        let (width, height) = self.canvas_size(canvas_id, scale)?;
+        self.render_canvas_target(canvas_id, RenderTarget::new(width, height, scale))
+    }
+
+    /// flattens the canvas on the given frame of the current timeline
+    /// the track data with the cel specifications isn't decoded, so animation folders fall back to the cel their
+    /// track labels name for that frame (see `Timeline::label_cel_at`), folders without labels and everything else
+    /// render like `render_canvas`
+    /// None if the file has no timeline
+    pub fn render_frame(&self, canvas_id: i64, frame: i64, scale: f64) -> Option<RgbaImage> {
+        let timeline = self.db().get_current_timeline()?;
+        self.render_timeline_frame(canvas_id, &timeline, frame, scale)
+    }
+
+    /// like `render_frame` for a specific timeline
+    pub fn render_timeline_frame(&self, canvas_id: i64, timeline: &Timeline, frame: i64, scale: f64) -> Option<RgbaImage> {
+        let (width, height) = self.canvas_size(canvas_id, scale)?;
+        let target = RenderTarget { frame: Some((timeline, frame)), ..RenderTarget::new(width, height, scale) };
+
+        self.render_canvas_target(canvas_id, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::chunks::exta::offscreen::{BlockData, BlockDataChunk};
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut e = ZlibEncoder::new(vec![], Compression::default());
+        e.write_all(data).unwrap();
+        e.finish().unwrap()
+    }
+
+    fn attr() -> OffscreenAttribute {
+        OffscreenAttribute { width: 2, height: 2, block_columns: 1, block_rows: 1, init_color: None }
+    }
+
+    fn chunk(zlib_data: &[u8], decompressed_size: u32) -> BlockDataChunk<'_> {
+        BlockDataChunk { index: 0, decompressed_size, width: 2, height: 2, data: Some(BlockData { prefix: [0; 4], zlib_data }) }
+    }
+
+    #[test]
+    fn decode_colour_block() {
+        // alpha plane then bgra
+        let mut data = vec![255, 128, 0, 255];
+        data.extend([[255, 0, 0, 0], [0, 255, 0, 0], [0, 0, 255, 0], [1, 2, 3, 0]].concat());
+        let z = zlib(&data);
+
+        let img = decode_offscreen(&attr(), &ExtaOffscreen { chunks: vec![chunk(&z, 20)] });
+        assert_eq!(img.pixel(0, 0), [0, 0, 255, 255]);
+        assert_eq!(img.pixel(1, 0), [0, 255, 0, 128]);
+        assert_eq!(img.pixel(1, 1), [3, 2, 1, 255]);
+    }
+
+    #[test]
+    fn skip_short_and_corrupt_blocks() {
+        let short = zlib(&[255; 8]);
+        let img = decode_offscreen(&attr(), &ExtaOffscreen { chunks: vec![chunk(&short, 20)] });
+        assert_eq!(img, RgbaImage::new(2, 2));
+
+        let corrupt = [0x78, 0x9c, 0xff, 0xff, 0xff];
+        assert!(chunk(&corrupt, 20).decompress().is_none());
+        let img = decode_offscreen(&attr(), &ExtaOffscreen { chunks: vec![chunk(&corrupt, 20)] });
+        assert_eq!(img, RgbaImage::new(2, 2));
+    }
+}
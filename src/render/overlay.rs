@@ -0,0 +1,256 @@
+use std::f64::consts::PI;
+
+use crate::{ClipFile, Guide, PerspectiveRuler, RgbaImage, SpecialRuler, SpecialRulerKind};
+use crate::render::raster::fill_polygons;
+
+// shapes drawn over renders (rulers etc.), in canvas pixels
+// line widths are in output pixels so they stay thin at any scale
+
+const PERSPECTIVE_COLOR: [u8; 3] = [128, 64, 255];
+const EYE_LEVEL_COLOR: [u8; 3] = [255, 64, 128];
+const RULER_COLOR: [u8; 3] = [64, 160, 255];
+const GUIDE_COLOR: [u8; 3] = [0, 200, 200];
+const OVERLAY_OPACITY: f32 = 0.75;
+
+// directions drawn for radial (emit) rulers
+const EMIT_LINES: usize = 24;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum OverlayShape {
+    Line { from: (f64, f64), to: (f64, f64), color: [u8; 3] },
+    Circle { center: (f64, f64), radius: f64, color: [u8; 3] }, // radius in output pixels
+    Polyline { points: Vec<(f64, f64)>, closed: bool, color: [u8; 3] },
+}
+
+/// the part of the line `p + t * d` (t in t_min..t_max) within the rect, None if it misses
+pub(crate) fn clip_line(p: (f64, f64), d: (f64, f64), t_min: f64, t_max: f64, width: f64, height: f64) -> Option<((f64, f64), (f64, f64))> {
+    let (mut t0, mut t1) = (t_min, t_max);
+
+    // liang-barsky against each edge
+    for (dp, dist) in [(-d.0, p.0), (d.0, width - p.0), (-d.1, p.1), (d.1, height - p.1)] {
+        if dp.abs() <= f64::EPSILON {
+            if dist < 0.0 {
+                return None;
+            }
+            continue;
+        }
+
+        let t = dist / dp;
+        if dp < 0.0 { t0 = t0.max(t) } else { t1 = t1.min(t) }
+    }
+
+    let at = |t: f64| { (p.0 + d.0 * t, p.1 + d.1 * t) };
+    (t0 < t1 && t0.is_finite() && t1.is_finite()).then_some((at(t0), at(t1)))
+}
+
+/// guide lines (rays from finite vanishing points), eye levels and vanishing points of the rulers
+pub(crate) fn perspective_shapes(rulers: &[PerspectiveRuler], width: f64, height: f64) -> Vec<OverlayShape> {
+    let mut shapes = vec![];
+
+    for ruler in rulers {
+        let (p, d) = ruler.eye_level();
+        if let Some((from, to)) = clip_line(p, d, f64::NEG_INFINITY, f64::INFINITY, width, height) {
+            shapes.push(OverlayShape::Line { from, to, color: EYE_LEVEL_COLOR });
+        }
+
+        for vp in &ruler.vanishing_points {
+            let t_min = if vp.is_parallel() { f64::NEG_INFINITY } else { 0.0 };
+
+            for (p, d) in vp.guide_lines() {
+                if let Some((from, to)) = clip_line(p, d, t_min, f64::INFINITY, width, height) {
+                    shapes.push(OverlayShape::Line { from, to, color: PERSPECTIVE_COLOR });
+                }
+            }
+
+            if !vp.is_parallel() {
+                shapes.push(OverlayShape::Circle { center: vp.point, radius: 3.0, color: PERSPECTIVE_COLOR });
+            }
+        }
+    }
+
+    shapes
+}
+
+fn angle_dir(degrees: f64) -> (f64, f64) {
+    let (s, c) = degrees.to_radians().sin_cos();
+    (c, s)
+}
+
+fn ellipse(center: (f64, f64), radius: (f64, f64), degrees: f64) -> Vec<(f64, f64)> {
+    const STEPS: usize = 72;
+    let (s, c) = degrees.to_radians().sin_cos();
+
+    (0..STEPS).map(|k| {
+        let a = 2.0 * PI * k as f64 / STEPS as f64;
+        let (x, y) = (radius.0 * a.cos(), radius.1 * a.sin());
+        (center.0 + x * c - y * s, center.1 + x * s + y * c)
+    }).collect()
+}
+
+/// the geometry of special rulers, perspective rulers use `perspective_shapes`
+pub(crate) fn ruler_shapes(rulers: &[SpecialRuler], width: f64, height: f64) -> Vec<OverlayShape> {
+    let mut shapes = vec![];
+    let line = |p: (f64, f64), d: (f64, f64), t_min: f64, shapes: &mut Vec<OverlayShape>| {
+        if let Some((from, to)) = clip_line(p, d, t_min, f64::INFINITY, width, height) {
+            shapes.push(OverlayShape::Line { from, to, color: RULER_COLOR });
+        }
+    };
+
+    for ruler in rulers {
+        match &ruler.kind {
+            SpecialRulerKind::Parallel { center, angle } => line(*center, angle_dir(*angle), f64::NEG_INFINITY, &mut shapes),
+            SpecialRulerKind::Emit { center } => {
+                for k in 0..EMIT_LINES {
+                    line(*center, angle_dir(360.0 * k as f64 / EMIT_LINES as f64), 0.0, &mut shapes);
+                }
+            }
+            SpecialRulerKind::ConcentricCircle { center, radius, angle } => {
+                shapes.push(OverlayShape::Polyline { points: ellipse(*center, *radius, *angle), closed: true, color: RULER_COLOR });
+            }
+            SpecialRulerKind::Guide { horizontal, position } => {
+                let guide = Guide {
+                    id: ruler.id,
+                    layer_id: ruler.layer_id,
+                    horizontal: *horizontal,
+                    position: if *horizontal { position.1 } else { position.0 },
+                    snap: ruler.snap,
+                    guide_move: 0,
+                };
+                shapes.extend(guide_shapes(&[guide], width, height));
+            }
+            SpecialRulerKind::Symmetry { center, angle, lines, .. } => {
+                // lines evenly split the half turn, the first one at the ruler's angle
+                let lines = (*lines).max(1);
+                for k in 0..lines {
+                    line(*center, angle_dir(angle + 180.0 * k as f64 / lines as f64), f64::NEG_INFINITY, &mut shapes);
+                }
+            }
+            SpecialRulerKind::CurveParallel { points, .. }
+            | SpecialRulerKind::MultiCurve { points, .. }
+            | SpecialRulerKind::CurveEmit { points, .. } => {
+                if points.len() >= 2 {
+                    shapes.push(OverlayShape::Polyline { points: points.clone(), closed: false, color: RULER_COLOR });
+                }
+            }
+            SpecialRulerKind::Perspective(p) => shapes.extend(perspective_shapes(std::slice::from_ref(p), width, height)),
+        }
+    }
+
+    shapes
+}
+
+pub(crate) fn guide_shapes(guides: &[Guide], width: f64, height: f64) -> Vec<OverlayShape> {
+    guides.iter().filter_map(|g| {
+        let (p, d) = g.line();
+        let (from, to) = clip_line(p, d, f64::NEG_INFINITY, f64::INFINITY, width, height)?;
+        Some(OverlayShape::Line { from, to, color: GUIDE_COLOR })
+    }).collect()
+}
+
+fn line_polygon(from: (f64, f64), to: (f64, f64), width: f64) -> Vec<(f64, f64)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+    let (nx, ny) = (-dy / len * width / 2.0, dx / len * width / 2.0);
+
+    vec![(from.0 + nx, from.1 + ny), (to.0 + nx, to.1 + ny), (to.0 - nx, to.1 - ny), (from.0 - nx, from.1 - ny)]
+}
+
+fn ring(center: (f64, f64), radius: f64, reverse: bool) -> Vec<(f64, f64)> {
+    const STEPS: usize = 24;
+    (0..STEPS).map(|k| {
+        let k = if reverse { STEPS - k } else { k };
+        let a = 2.0 * PI * k as f64 / STEPS as f64;
+        (center.0 + radius * a.cos(), center.1 + radius * a.sin())
+    }).collect()
+}
+
+/// draws the shapes over an image rendered at `scale`
+pub(crate) fn draw_overlay(img: &mut RgbaImage, shapes: &[OverlayShape], scale: f64) {
+    let scaled = |p: (f64, f64)| { (p.0 * scale, p.1 * scale) };
+
+    for shape in shapes {
+        match *shape {
+            OverlayShape::Polyline { ref points, closed, color } => {
+                let n = if closed { points.len() } else { points.len().saturating_sub(1) };
+                let polys: Vec<Vec<(f64, f64)>> = (0..n).map(|i| {
+                    line_polygon(scaled(points[i]), scaled(points[(i + 1) % points.len()]), 1.0)
+                }).collect();
+
+                // one fill so overlapping segment ends don't double up
+                fill_polygons(img, &polys, color, OVERLAY_OPACITY, true);
+            }
+            OverlayShape::Line { from, to, color } => {
+                let poly = line_polygon(scaled(from), scaled(to), 1.0);
+                fill_polygons(img, &[poly], color, OVERLAY_OPACITY, true);
+            }
+            OverlayShape::Circle { center, radius, color } => {
+                let center = scaled(center);
+                let polys = [ring(center, radius + 0.5, false), ring(center, radius - 0.5, true)];
+                fill_polygons(img, &polys, color, OVERLAY_OPACITY, true);
+            }
+        }
+    }
+}
+
+impl ClipFile {
+    /// perspective rulers of every layer in the canvas
+    pub fn perspective_rulers(&self, canvas_id: i64) -> Vec<PerspectiveRuler> {
+        let db = self.db();
+
+        db.get_layer_ids_for_canvas(canvas_id).into_iter()
+            .filter_map(|id| { db.get_layer(id)?.special_ruler_manager_id })
+            .flat_map(|manager| { db.get_perspective_rulers(manager) })
+            .collect()
+    }
+
+    pub(crate) fn perspective_overlay(&self, canvas_id: i64) -> Option<Vec<OverlayShape>> {
+        let (width, height) = self.db().get_canvas(canvas_id)?.size_px();
+        Some(perspective_shapes(&self.perspective_rulers(canvas_id), width as f64, height as f64))
+    }
+
+    /// draws the canvas' perspective rulers (eye level, guide lines and vanishing points)
+    /// over an image rendered from it at `scale`
+    pub fn draw_perspective_rulers(&self, canvas_id: i64, img: &mut RgbaImage, scale: f64) {
+        if let Some(shapes) = self.perspective_overlay(canvas_id) {
+            draw_overlay(img, &shapes, scale);
+        }
+    }
+
+    /// special rulers of every layer in the canvas (perspective rulers included)
+    pub fn special_rulers(&self, canvas_id: i64) -> Vec<SpecialRuler> {
+        let db = self.db();
+
+        db.get_layer_ids_for_canvas(canvas_id).into_iter()
+            .filter_map(|id| { db.get_layer(id)?.special_ruler_manager_id })
+            .flat_map(|manager| { db.get_special_rulers(manager) })
+            .collect()
+    }
+
+    pub(crate) fn ruler_overlay(&self, canvas_id: i64) -> Option<Vec<OverlayShape>> {
+        let (width, height) = self.db().get_canvas(canvas_id)?.size_px();
+        Some(ruler_shapes(&self.special_rulers(canvas_id), width as f64, height as f64))
+    }
+
+    /// draws all of the canvas' special rulers over an image rendered from it at `scale`
+    pub fn draw_special_rulers(&self, canvas_id: i64, img: &mut RgbaImage, scale: f64) {
+        if let Some(shapes) = self.ruler_overlay(canvas_id) {
+            draw_overlay(img, &shapes, scale);
+        }
+    }
+
+    /// guides of every layer in the canvas
+    pub fn guides(&self, canvas_id: i64) -> Vec<Guide> {
+        let db = self.db();
+
+        db.get_layer_ids_for_canvas(canvas_id).into_iter()
+            .filter_map(|id| { db.get_layer(id)?.special_ruler_manager_id })
+            .flat_map(|manager| { db.get_guides(manager) })
+            .collect()
+    }
+
+    /// draws the canvas' guides over an image rendered from it at `scale`
+    pub fn draw_guides(&self, canvas_id: i64, img: &mut RgbaImage, scale: f64) {
+        let Some(canvas) = self.db().get_canvas(canvas_id) else { return };
+        draw_overlay(img, &guide_shapes(&self.guides(canvas_id), canvas.width, canvas.height), scale);
+    }
+}
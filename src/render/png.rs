@@ -0,0 +1,26 @@
+use png::{ColorType, Decoder, Transformations};
+
+use crate::render::RgbaImage;
+
+impl RgbaImage {
+    /// decodes a png, any colour type or bit depth is converted to 8 bit rgba
+    pub fn from_png(data: &[u8]) -> Option<RgbaImage> {
+        let mut decoder = Decoder::new(data);
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+
+        let mut reader = decoder.read_info().ok()?;
+        let mut buf = vec![0u8; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).ok()?;
+        let pixels = &buf[..info.buffer_size()];
+
+        let data = match info.color_type {
+            ColorType::Rgba => pixels.to_vec(),
+            ColorType::Rgb => pixels.chunks_exact(3).flat_map(|p| { [p[0], p[1], p[2], 255] }).collect(),
+            ColorType::GrayscaleAlpha => pixels.chunks_exact(2).flat_map(|p| { [p[0], p[0], p[0], p[1]] }).collect(),
+            ColorType::Grayscale => pixels.iter().flat_map(|p| { [*p, *p, *p, 255] }).collect(),
+            ColorType::Indexed => return None, // expanded by the decoder
+        };
+
+        Some(RgbaImage { width: info.width, height: info.height, data })
+    }
+}
@@ -0,0 +1,105 @@
+use crate::render::RgbaImage;
+use crate::vector::{stroke_opacity, stroke_outline};
+use crate::VectorStroke;
+
+// vertical sub scanlines per pixel, horizontal coverage is computed exactly
+const SUBSAMPLES: usize = 4;
+
+/// per pixel coverage of the polygons (nonzero fill rule) within the image bounds
+/// returns the covered rect (x, y, width) and its coverage values
+fn coverage(polys: &[Vec<(f64, f64)>], width: u32, height: u32) -> Option<(usize, usize, usize, Vec<f32>)> {
+    let points = || { polys.iter().flatten() };
+
+    let min_x = points().map(|p| { p.0 }).fold(f64::INFINITY, f64::min).floor().max(0.0) as usize;
+    let min_y = points().map(|p| { p.1 }).fold(f64::INFINITY, f64::min).floor().max(0.0) as usize;
+    let max_x = (points().map(|p| { p.0 }).fold(f64::NEG_INFINITY, f64::max).ceil().min(width as f64)) as isize;
+    let max_y = (points().map(|p| { p.1 }).fold(f64::NEG_INFINITY, f64::max).ceil().min(height as f64)) as isize;
+
+    if max_x <= min_x as isize || max_y <= min_y as isize {
+        return None;
+    }
+
+    let (w, h) = (max_x as usize - min_x, max_y as usize - min_y);
+    let mut cov = vec![0f32; w * h];
+
+    // (x, winding direction)
+    let mut crossings: Vec<(f64, i32)> = vec![];
+
+    for row in 0..h {
+        for sub in 0..SUBSAMPLES {
+            let sy = (min_y + row) as f64 + (sub as f64 + 0.5) / SUBSAMPLES as f64;
+
+            crossings.clear();
+            for poly in polys {
+                for (i, &(x0, y0)) in poly.iter().enumerate() {
+                    let (x1, y1) = poly[(i + 1) % poly.len()];
+                    if (y0 <= sy) == (y1 <= sy) {
+                        continue;
+                    }
+
+                    let x = x0 + (sy - y0) / (y1 - y0) * (x1 - x0);
+                    crossings.push((x - min_x as f64, if y1 > y0 { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| { a.0.total_cmp(&b.0) });
+
+            let line = &mut cov[row * w..][..w];
+            let mut winding = 0;
+
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+
+                let (x0, x1) = (pair[0].0.clamp(0.0, w as f64), pair[1].0.clamp(0.0, w as f64));
+                if x1 <= x0 {
+                    continue;
+                }
+
+                let weight = 1.0 / SUBSAMPLES as f32;
+                let (first, last) = (x0.floor() as usize, (x1.ceil() as usize).min(w));
+
+                for (px, c) in line.iter_mut().enumerate().take(last).skip(first) {
+                    let span = x1.min(px as f64 + 1.0) - x0.max(px as f64);
+                    *c += span as f32 * weight;
+                }
+            }
+        }
+    }
+
+    Some((min_x, min_y, w, cov))
+}
+
+/// fills the polygons (nonzero rule) over the image with normal blending
+pub(crate) fn fill_polygons(img: &mut RgbaImage, polys: &[Vec<(f64, f64)>], color: [u8; 3], opacity: f32, antialias: bool) {
+    let Some((ox, oy, w, cov)) = coverage(polys, img.width, img.height) else { return };
+    let color = color.map(|c| { c as f32 / 255.0 });
+
+    for (i, c) in cov.iter().enumerate() {
+        let c = c.min(1.0);
+        let c = if antialias { c } else if c >= 0.5 { 1.0 } else { 0.0 };
+
+        let sa = c * opacity;
+        if sa <= 0.0 {
+            continue;
+        }
+
+        let (x, y) = (ox + i % w, oy + i / w);
+        let p = &mut img.data[(y * img.width as usize + x) * 4..][..4];
+
+        let da = p[3] as f32 / 255.0;
+        let a = sa + da * (1.0 - sa);
+
+        for ch in 0..3 {
+            let d = p[ch] as f32 / 255.0;
+            p[ch] = ((color[ch] * sa + d * da * (1.0 - sa)) / a * 255.0).round() as u8;
+        }
+        p[3] = (a * 255.0).round() as u8;
+    }
+}
+
+/// draws the stroke over the image with normal blending
+pub(crate) fn draw_stroke(img: &mut RgbaImage, stroke: &VectorStroke) {
+    fill_polygons(img, &stroke_outline(stroke), stroke.main_color, stroke_opacity(stroke) as f32, stroke.is_antialiased());
+}
@@ -0,0 +1,57 @@
+use crate::{ClipFile, LayerThumbnail, ThumbnailSize};
+use crate::render::RgbaImage;
+
+/// a layer thumbnail along with the info CSP stored for it
+#[derive(Debug, Clone)]
+pub struct LayerThumbnailImage {
+    pub image: RgbaImage,
+    pub info: Option<LayerThumbnail>,
+    pub rendered: bool, // the stored thumbnail was missing or outdated so the layer was rendered instead
+}
+
+impl ClipFile {
+    fn stored_thumbnail(&self, info: &LayerThumbnail, size: ThumbnailSize) -> Option<RgbaImage> {
+        if info.needs_refresh(size) {
+            return None;
+        }
+
+        let (x, y, w, h) = size.region();
+        let offscreen = self.render_offscreen(info.offscreen_id)?;
+
+        let mut img = RgbaImage::new(w, h);
+        img.paste(&offscreen, -(x as i64), -(y as i64));
+        Some(img)
+    }
+
+    // the layer scaled down to fit a square of the given size, centred
+    fn rendered_thumbnail(&self, layer_id: i64, size: u32) -> Option<RgbaImage> {
+        let layer = self.db().get_layer(layer_id)?;
+        let canvas = self.db().get_canvas(layer.canvas_id)?;
+
+        let longest = canvas.width.max(canvas.height);
+        if !longest.is_finite() || longest <= 0.0 {
+            return None;
+        }
+
+        let scale = size as f64 / longest;
+        let render = self.render_layer(layer_id, scale)?;
+
+        let mut img = RgbaImage::new(size, size);
+        img.paste(&render, (size as i64 - render.width as i64) / 2, (size as i64 - render.height as i64) / 2);
+        Some(img)
+    }
+
+    /// the thumbnail CSP shows in the layer list at the given size
+    /// falls back to rendering the layer when the stored one is missing or needs a refresh
+    pub fn layer_thumbnail(&self, layer_id: i64, size: ThumbnailSize) -> Option<LayerThumbnailImage> {
+        let info = self.db().get_layer_thumbnail(layer_id);
+
+        if let Some(image) = info.as_ref().and_then(|i| { self.stored_thumbnail(i, size) }) {
+            return Some(LayerThumbnailImage { image, info, rendered: false });
+        }
+
+        let image = self.rendered_thumbnail(layer_id, size.pixels())?;
+
+        Some(LayerThumbnailImage { image, info, rendered: true })
+    }
+}
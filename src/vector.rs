@@ -0,0 +1,206 @@
+use std::f64::consts::PI;
+
+use crate::VectorStroke;
+
+// stroke geometry shared by svg export and rasterization
+// strokes are treated as catmull-rom splines through their control points (they look close enough)
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct StrokeSample {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub opacity: f64,
+}
+
+/// most samples taken along a single segment, a canvas diagonal is far below this
+const MAX_SEGMENT_STEPS: usize = 1 << 16;
+
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2.0 * p1) + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// samples along the stroke roughly every half pixel
+pub(crate) fn sample_stroke(stroke: &VectorStroke) -> Vec<StrokeSample> {
+    let pts: Vec<StrokeSample> = stroke.points.iter().map(|p| {
+        StrokeSample {
+            x: p.x,
+            y: p.y,
+            width: stroke.brush_size * (p.width * p.pressure) as f64,
+            opacity: stroke.opacity * p.opacity as f64,
+        }
+    }).collect();
+
+    let n = pts.len();
+    if n < 2 {
+        return pts;
+    }
+
+    let closed = stroke.is_closed();
+    let at = |i: isize| -> StrokeSample {
+        if closed { pts[i.rem_euclid(n as isize) as usize] } else { pts[i.clamp(0, n as isize - 1) as usize] }
+    };
+
+    let segments = if closed { n } else { n - 1 };
+    let mut out = vec![];
+
+    for s in 0..segments as isize {
+        let (p0, p1, p2, p3) = (at(s - 1), at(s), at(s + 1), at(s + 2));
+        let len = ((p2.x - p1.x).powi(2) + (p2.y - p1.y).powi(2)).sqrt();
+        if !len.is_finite() {
+            continue;
+        }
+
+        let steps = ((len * 2.0).ceil() as usize).clamp(4, MAX_SEGMENT_STEPS);
+
+        for k in 0..steps {
+            let t = k as f64 / steps as f64;
+            out.push(StrokeSample {
+                x: catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+                y: catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+                width: p1.width + (p2.width - p1.width) * t,
+                opacity: p1.opacity + (p2.opacity - p1.opacity) * t,
+            });
+        }
+    }
+
+    if !closed {
+        out.push(pts[n - 1]);
+    }
+
+    out
+}
+
+/// the stroke with its points and brush size scaled
+pub(crate) fn scale_stroke(stroke: &VectorStroke, scale: f64) -> VectorStroke {
+    let bounds = |b: [i32; 4]| { b.map(|v| { (v as f64 * scale).round() as i32 }) };
+
+    let mut stroke = stroke.clone();
+    stroke.brush_size *= scale;
+    stroke.bounds = bounds(stroke.bounds);
+
+    for p in &mut stroke.points {
+        p.x *= scale;
+        p.y *= scale;
+        p.bounds = bounds(p.bounds);
+    }
+
+    stroke
+}
+
+/// overall opacity of the stroke, per point opacity is averaged since outlines are filled with a single colour
+pub(crate) fn stroke_opacity(stroke: &VectorStroke) -> f64 {
+    let points = stroke.points.iter().map(|p| { p.opacity as f64 }).sum::<f64>() / stroke.points.len().max(1) as f64;
+    stroke.opacity * points
+}
+
+// unit normal of the direction at sample i
+fn normal(s: &[StrokeSample], i: usize, closed: bool) -> (f64, f64) {
+    let n = s.len();
+    let (a, b) = if closed {
+        (s[(i + n - 1) % n], s[(i + 1) % n])
+    } else {
+        (s[i.saturating_sub(1)], s[(i + 1).min(n - 1)])
+    };
+
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len = (dx * dx + dy * dy).sqrt();
+
+    if len <= f64::EPSILON { (0.0, 0.0) } else { (-dy / len, dx / len) }
+}
+
+fn arc(cx: f64, cy: f64, r: f64, from: f64, to: f64) -> impl Iterator<Item = (f64, f64)> {
+    const STEPS: usize = 8;
+    (1..STEPS).map(move |k| {
+        let a = from + (to - from) * k as f64 / STEPS as f64;
+        (cx + r * a.cos(), cy + r * a.sin())
+    })
+}
+
+/// variable width outline of the stroke as polygons (to be filled with the nonzero rule)
+/// open strokes are one polygon with round caps, closed ones are an outer and a reversed inner ring
+pub(crate) fn stroke_outline(stroke: &VectorStroke) -> Vec<Vec<(f64, f64)>> {
+    let s = sample_stroke(stroke);
+    let closed = stroke.is_closed();
+
+    if s.is_empty() {
+        return vec![];
+    }
+
+    if s.len() == 1 {
+        let p = s[0];
+        return vec![arc(p.x, p.y, p.width / 2.0, 0.0, 2.0 * PI).collect()];
+    }
+
+    let side = |sign: f64| -> Vec<(f64, f64)> {
+        (0..s.len()).map(|i| {
+            let (nx, ny) = normal(&s, i, closed);
+            let r = s[i].width / 2.0 * sign;
+            (s[i].x + nx * r, s[i].y + ny * r)
+        }).collect()
+    };
+
+    let left = side(1.0);
+    let mut right = side(-1.0);
+    right.reverse();
+
+    if closed {
+        return vec![left, right];
+    }
+
+    // round caps, going from the left side to the right side around each end
+    let (first, last) = (s[0], s[s.len() - 1]);
+    let (fnx, fny) = normal(&s, 0, false);
+    let (lnx, lny) = normal(&s, s.len() - 1, false);
+
+    let end_angle = lny.atan2(lnx);
+    let start_angle = (-fny).atan2(-fnx);
+
+    let mut poly = left;
+    poly.extend(arc(last.x, last.y, last.width / 2.0, end_angle, end_angle - PI));
+    poly.extend(right);
+    poly.extend(arc(first.x, first.y, first.width / 2.0, start_angle, start_angle - PI));
+
+    vec![poly]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VectorPoint;
+
+    fn stroke(points: &[(f64, f64)]) -> VectorStroke {
+        VectorStroke {
+            flags: 0,
+            bounds: [0; 4],
+            main_color: [0; 3],
+            sub_color: [0; 3],
+            opacity: 1.0,
+            antialias: 0,
+            brush_size: 2.0,
+            points: points.iter().map(|&(x, y)| {
+                VectorPoint { x, y, bounds: [0; 4], pressure: 1.0, width: 1.0, opacity: 1.0 }
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn samples_every_half_pixel() {
+        let s = sample_stroke(&stroke(&[(0.0, 0.0), (10.0, 0.0)]));
+
+        assert_eq!(s.len(), 21);
+        assert_eq!((s[20].x, s[20].y), (10.0, 0.0));
+        assert!(s.iter().all(|p| { p.width == 2.0 && p.opacity == 1.0 }));
+    }
+
+    #[test]
+    fn degenerate_segments() {
+        let s = sample_stroke(&stroke(&[(0.0, 0.0), (f64::INFINITY, 0.0), (f64::NAN, 1.0)]));
+        assert!(s.len() <= 3);
+
+        let s = sample_stroke(&stroke(&[(0.0, 0.0), (1e300, 0.0)]));
+        assert!(s.len() <= MAX_SEGMENT_STEPS + 1);
+    }
+}
@@ -0,0 +1,125 @@
+use clipdecode::{Capability, ClipDb, ClipFile, SchemaCache};
+use rusqlite::Connection;
+
+fn open(name: &str) -> ClipFile {
+    ClipFile::open(format!("{}/assets/{name}.clip", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+#[test]
+fn schema_lookups() {
+    let file = open("layer-types");
+
+    // twice so the second one comes from the cache
+    for _ in 0..2 {
+        let db = file.db();
+        assert!(db.table_exists("Layer"));
+        assert!(!db.table_exists("NoSuchTable"));
+        assert!(db.column_exists("Layer", "LayerName"));
+        assert!(!db.column_exists("Layer", "NoSuchColumn"));
+        assert!(db.columns("Layer").iter().any(|c| { c == "MainId" }));
+        assert!(db.columns("NoSuchTable").is_empty());
+    }
+
+    assert!(file.db().has_capability(Capability::Vectors));
+    assert!(!open("colors").db().has_capability(Capability::Vectors));
+}
+
+#[test]
+fn schema_cache_is_shared() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE Canvas (MainId INTEGER);").unwrap();
+
+    let schema = SchemaCache::default();
+    assert!(!ClipDb::with_cache(&conn, &schema).column_exists("Canvas", "CanvasWidth"));
+
+    // the second ClipDb answers from the cache the first one filled
+    conn.execute_batch("ALTER TABLE Canvas ADD COLUMN CanvasWidth REAL;").unwrap();
+    assert!(!ClipDb::with_cache(&conn, &schema).column_exists("Canvas", "CanvasWidth"));
+    assert!(ClipDb::with_conn(&conn).column_exists("Canvas", "CanvasWidth"));
+}
+
+#[test]
+fn project_without_default_page_columns() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("CREATE TABLE Project (MainId INTEGER, ProjectInternalVersion TEXT, ProjectName TEXT, ProjectCanvas INTEGER, \
+        DefaultPageWidth REAL); INSERT INTO Project VALUES (1, '1.0.0', '1', 1, 600.0);").unwrap();
+
+    let project = ClipDb::with_conn(&conn).get_project().unwrap();
+    assert_eq!(project.canvas_id, 1);
+    assert_eq!(project.cut_bank_id, None);
+    assert_eq!(project.default_page.width, Some(600.0));
+    assert_eq!(project.default_page.paper_color, None);
+    assert_eq!(project.default_page.use_paper, None);
+
+    let page = open("colors").db().get_project().unwrap().default_page;
+    assert_eq!(page.paper_color, Some([255, 255, 255]));
+}
+
+#[test]
+fn links_without_flags_are_followed() {
+    let file = open("layer-types");
+    let db = file.db();
+
+    let project = db.rows("Project").next().unwrap();
+    let canvas = db.follow_link(&project, "ProjectCanvas").unwrap();
+    assert_eq!(canvas.table, "Canvas");
+    assert_eq!(canvas.main_id(), Some(1));
+}
+
+#[test]
+fn vector_indices_are_object_list_ids() {
+    let file = open("layer-types");
+    let db = file.db();
+
+    // stroke index 5 on layer 34 is VectorObjectList row 5
+    let layer = db.row("Layer", 34).unwrap();
+    let strokes = db.follow_link(&layer, "VectorNormalStrokeIndex").unwrap();
+    assert_eq!(strokes.table, "VectorObjectList");
+    assert_eq!(strokes.main_id(), Some(5));
+    assert_eq!(strokes.get("LayerId").and_then(|v| { v.as_i64() }), Some(34));
+
+    // a fill index of 0 is no fill, not the first fill style
+    for id in [6, 17, 34] {
+        assert_eq!(db.get_layer(id).unwrap().vector_fill_index, Some(0));
+        assert!(db.get_layer_fill_object(id).is_none());
+        assert!(db.get_layer_fill_style(id).is_none());
+    }
+
+    assert!(db.get_fill_style(2).is_some());
+}
+
+#[test]
+fn brush_effectors_from_assets() {
+    let file = open("layer-types");
+    let db = file.db();
+
+    // the effectors in the samples are only flags, pressure on the size and opacity of brush 3
+    let styles = db.get_brush_styles_for_canvas(1);
+    let style = styles.iter().find(|s| { s.id == 3 }).unwrap();
+    assert_eq!(style.size.flags, 1);
+    assert_eq!(style.opacity.flags, 1);
+
+    for style in &styles {
+        assert!(style.effectors().iter().all(|(_, e)| { e.curve_data.is_empty() }));
+    }
+}
+
+#[test]
+fn fill_style_through_fill_object() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch("\
+        CREATE TABLE ParamScheme (TableName TEXT, LabelName TEXT, DataType INTEGER, Flag INTEGER, OwnerType INTEGER, LinkTable TEXT); \
+        INSERT INTO ParamScheme VALUES ('Layer', 'VectorNormalFillIndex', 1, 4, 0, 'VectorObjectList'); \
+        INSERT INTO ParamScheme VALUES ('VectorObjectList', 'FillStyleIndex', 1, 4, 0, 'FillStyle'); \
+        CREATE TABLE Layer (MainId INTEGER, VectorNormalFillIndex INTEGER); \
+        INSERT INTO Layer VALUES (1, 3), (2, 4), (3, 0); \
+        CREATE TABLE VectorObjectList (MainId INTEGER, LayerId INTEGER, FillStyleIndex INTEGER); \
+        INSERT INTO VectorObjectList VALUES (3, 1, 2), (4, 2, 0); \
+        CREATE TABLE FillStyle (MainId INTEGER, CanvasId INTEGER, NextIndex INTEGER); \
+        INSERT INTO FillStyle VALUES (1, 1, 2), (2, 1, 0);").unwrap();
+
+    let db = ClipDb::with_conn(&conn);
+    assert_eq!(db.get_layer_fill_style(1).map(|s| { s.id }), Some(2));
+    assert!(db.get_layer_fill_style(2).is_none());
+    assert!(db.get_layer_fill_style(3).is_none());
+}
@@ -0,0 +1,24 @@
+use clipdecode::ClipFile;
+
+fn open(name: &str) -> ClipFile {
+    ClipFile::open(format!("{}/assets/{name}.clip", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+#[test]
+fn tiny_scale_exports_a_pixel() {
+    let file = open("layer-types");
+    let png = file.export_canvas_png(1, 0.0001).unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+    let img = file.render_canvas(1, 0.01).unwrap();
+    assert!(img.width >= 1 && img.height >= 1);
+}
+
+#[test]
+fn invalid_scale_exports_nothing() {
+    let file = open("layer-types");
+    for scale in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert!(file.export_canvas_png(1, scale).is_none());
+        assert!(file.render_canvas(1, scale).is_none());
+    }
+}